@@ -0,0 +1,65 @@
+//! Stateless, signed API tokens for the JSON API (as opposed to the cookie-based `sessions` used by the web UI).
+//!
+//! A token is just `user`, `key_id`, and `issued_at` HMAC-signed with a secret held on [`State`](crate::State),
+//! so verifying one never needs a database round trip. The `api_tokens` table only has to be consulted to
+//! check that `key_id` hasn't been explicitly revoked (and to look up the token's owner).
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::model::UserId;
+use crate::time::{now, Timestamp};
+use crate::util::constant_time_eq;
+
+pub type Secret = [u8; 32];
+
+#[derive(Serialize, Deserialize)]
+struct Payload {
+	user: UserId,
+	key_id: i64,
+	issued_at: Timestamp,
+}
+
+fn sign(secret: &Secret, payload: &[u8]) -> [u8; 32] {
+	let mut mac =
+		<Hmac<Sha256>>::new_from_slice(secret).expect("HMAC-SHA256 accepts keys of any length");
+	mac.update(payload);
+	mac.finalize().into_bytes().into()
+}
+
+/// Mints a signed token for `user`/`key_id`. `key_id` should be the id of the corresponding
+/// `api_tokens` row, so that revoking that row invalidates the token despite it being otherwise stateless.
+pub fn encode(secret: &Secret, user: UserId, key_id: i64) -> String {
+	let payload = Payload {
+		user,
+		key_id,
+		issued_at: now(),
+	};
+	let payload = serde_json::to_vec(&payload).expect("serializing api token payload");
+	let tag = sign(secret, &payload);
+	format!("{}.{}", hex::encode(payload), hex::encode(tag))
+}
+
+pub struct Decoded {
+	pub user: UserId,
+	pub key_id: i64,
+	pub issued_at: Timestamp,
+}
+
+/// Verifies the signature and shape of `raw` only. Callers must still check the `api_tokens` table
+/// to make sure `key_id` hasn't been revoked.
+pub fn decode(secret: &Secret, raw: &str) -> Option<Decoded> {
+	let (payload_hex, tag_hex) = raw.split_once('.')?;
+	let payload = hex::decode(payload_hex).ok()?;
+	let tag = hex::decode(tag_hex).ok()?;
+	if !constant_time_eq(&tag, &sign(secret, &payload)) {
+		return None;
+	}
+	let payload: Payload = serde_json::from_slice(&payload).ok()?;
+	Some(Decoded {
+		user: payload.user,
+		key_id: payload.key_id,
+		issued_at: payload.issued_at,
+	})
+}
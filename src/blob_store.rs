@@ -0,0 +1,166 @@
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use axum::async_trait;
+use sha2::{Digest, Sha256};
+use sqlx::{query, query_scalar, SqlitePool};
+
+use crate::config::HttpBlobStorage;
+use crate::util::sqlx_type_via;
+
+/// A content-addressed key for a blob: the hex-encoded SHA-256 digest of its bytes.
+/// Storing the same bytes twice yields the same key, so identical blobs are deduplicated for free.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlobKey([u8; 32]);
+
+impl BlobKey {
+	fn of(data: &[u8]) -> Self {
+		Self(Sha256::digest(data).into())
+	}
+
+	fn repr(&self) -> String {
+		hex::encode(self.0)
+	}
+}
+
+impl Display for BlobKey {
+	fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+		formatter.write_str(&self.repr())
+	}
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("invalid blob key {0:?}")]
+pub struct BlobKeyFromStrError(Box<str>);
+
+impl FromStr for BlobKey {
+	type Err = BlobKeyFromStrError;
+
+	fn from_str(raw: &str) -> Result<Self, Self::Err> {
+		let mut buf = [0; 32];
+		hex::decode_to_slice(raw, &mut buf).map_err(|_error| BlobKeyFromStrError(raw.into()))?;
+		Ok(Self(buf))
+	}
+}
+
+impl TryFrom<String> for BlobKey {
+	type Error = BlobKeyFromStrError;
+
+	fn try_from(raw: String) -> Result<Self, Self::Error> {
+		raw.parse()
+	}
+}
+
+sqlx_type_via!(BlobKey as String);
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+	#[error("blob storage database error: {0}")]
+	Sqlx(#[from] sqlx::Error),
+	#[error("blob storage request failed: {0}")]
+	Http(#[from] reqwest::Error),
+	#[error("blob storage responded with status {0}")]
+	BadStatus(reqwest::StatusCode),
+	#[error("blob {0} not found in storage")]
+	NotFound(BlobKey),
+}
+
+/// Content-addressed storage for large blobs (test cases, submitted code) that would otherwise
+/// bloat every row read out of the main database.
+#[async_trait]
+pub trait BlobStore: Send + Sync {
+	/// Stores `data` and returns the key it can later be fetched by.
+	async fn put(&self, data: &[u8]) -> Result<BlobKey, Error>;
+
+	/// Fetches the blob previously stored under `key`.
+	async fn get(&self, key: BlobKey) -> Result<Vec<u8>, Error>;
+}
+
+/// Default backend: blobs live in a table in the same SQLite database as everything else.
+pub struct SqliteBlobStore {
+	database: SqlitePool,
+}
+
+impl SqliteBlobStore {
+	pub fn new(database: SqlitePool) -> Self {
+		Self { database }
+	}
+}
+
+#[async_trait]
+impl BlobStore for SqliteBlobStore {
+	async fn put(&self, data: &[u8]) -> Result<BlobKey, Error> {
+		let key = BlobKey::of(data);
+		query!(
+			"insert into blobs (key, data) values (?, ?) on conflict (key) do nothing",
+			key,
+			data,
+		)
+		.execute(&self.database)
+		.await?;
+		Ok(key)
+	}
+
+	async fn get(&self, key: BlobKey) -> Result<Vec<u8>, Error> {
+		query_scalar!("select data from blobs where key = ?", key)
+			.fetch_optional(&self.database)
+			.await?
+			.ok_or(Error::NotFound(key))
+	}
+}
+
+/// Alternate backend for an S3/Backblaze-style HTTP object store, selected via `config.toml`.
+/// Objects are addressed by their `BlobKey` as the path under `endpoint`.
+pub struct HttpBlobStore {
+	client: reqwest::Client,
+	endpoint: String,
+	access_key: String,
+	secret_key: String,
+}
+
+impl HttpBlobStore {
+	pub fn new(config: &HttpBlobStorage) -> Self {
+		Self {
+			client: reqwest::Client::new(),
+			endpoint: config.endpoint.clone(),
+			access_key: config.access_key.clone(),
+			secret_key: config.secret_key.clone(),
+		}
+	}
+
+	fn object_url(&self, key: BlobKey) -> String {
+		format!("{}/{key}", self.endpoint)
+	}
+}
+
+#[async_trait]
+impl BlobStore for HttpBlobStore {
+	async fn put(&self, data: &[u8]) -> Result<BlobKey, Error> {
+		let key = BlobKey::of(data);
+		let response = self
+			.client
+			.put(self.object_url(key))
+			.basic_auth(&self.access_key, Some(&self.secret_key))
+			.body(data.to_vec())
+			.send()
+			.await?;
+		if !response.status().is_success() {
+			return Err(Error::BadStatus(response.status()));
+		}
+		Ok(key)
+	}
+
+	async fn get(&self, key: BlobKey) -> Result<Vec<u8>, Error> {
+		let response = self
+			.client
+			.get(self.object_url(key))
+			.basic_auth(&self.access_key, Some(&self.secret_key))
+			.send()
+			.await?;
+		match response.status() {
+			status if status.is_success() => Ok(response.bytes().await?.to_vec()),
+			reqwest::StatusCode::NOT_FOUND => Err(Error::NotFound(key)),
+			status => Err(Error::BadStatus(status)),
+		}
+	}
+}
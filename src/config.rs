@@ -7,6 +7,62 @@ pub struct Config {
 	pub admin_email: String,
 	pub smtp: Smtp,
 	pub address: BindableAddr,
+	pub submission_rate_limit: RateLimit,
+	/// Applies per client (see `ratelimit::client_key`) to the admin SQL console.
+	pub sql_rate_limit: RateLimit,
+	/// Wall-clock limit, in seconds, for a "safe mode" query in the admin SQL console.
+	pub sql_console_timeout_secs: u64,
+	/// Applies per client to logging in and registering.
+	pub auth_rate_limit: RateLimit,
+	/// Number of background workers judging submissions concurrently.
+	pub judge_workers: u32,
+	/// Hex-encoded 32-byte HMAC key signing API tokens (see `api_token`). Keep this stable across
+	/// restarts, or every previously issued token stops verifying.
+	pub api_token_secret: String,
+	pub blob_storage: BlobStorage,
+	/// Single sign-on via external OAuth2/OIDC providers, in addition to local password login.
+	#[serde(default, rename = "oauth")]
+	pub oauth: Vec<OAuthProvider>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct OAuthProvider {
+	/// Identifies this provider in its login URL (`/oauth/:name/start`) and in `oauth_identities`.
+	pub name: String,
+	pub client_id: String,
+	pub client_secret: String,
+	pub authorize_url: String,
+	pub token_url: String,
+	pub userinfo_url: String,
+	pub scopes: Vec<String>,
+	/// If set, only accounts with one of these email domains can be auto-provisioned through this
+	/// provider. Linking to an existing account by verified email is unaffected.
+	pub allowed_email_domains: Option<Vec<String>>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct RateLimit {
+	/// Maximum number of requests that draw on this bucket before having to wait for a refill.
+	pub capacity: u32,
+	/// Time, in seconds, for a fully-drained bucket to refill to `capacity`.
+	pub window_seconds: u64,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(tag = "backend", rename_all = "snake_case")]
+pub enum BlobStorage {
+	/// Blobs live in a table in the main SQLite database.
+	Sqlite,
+	/// Blobs live in an S3/Backblaze-style HTTP object store.
+	Http(HttpBlobStorage),
+}
+
+#[derive(serde::Deserialize)]
+pub struct HttpBlobStorage {
+	/// Base URL of the bucket; blob keys are appended as path segments.
+	pub endpoint: String,
+	pub access_key: String,
+	pub secret_key: String,
 }
 
 #[derive(serde::Deserialize)]
@@ -29,6 +29,7 @@ fn constraint_message(name: &str) -> Option<&'static str> {
 pub struct ErrorResponse {
 	pub status: StatusCode,
 	pub message: String,
+	pub retry_after: Option<u64>,
 }
 
 impl ErrorResponse {
@@ -40,17 +41,44 @@ impl ErrorResponse {
 			message: format!(
 				"The error has been logged under ID {id}. Contact the administrator with this ID."
 			),
+			retry_after: None,
 		}
 	}
 
-	pub fn sqlx(error: sqlx::Error) -> Self {
-		if let sqlx::Error::Database(db_error) = &error {
+	/// `retry_after_secs` is surfaced to the client in a `Retry-After` header; `reason` is used as
+	/// the message as-is.
+	pub fn too_many_requests<T: Into<String>>(reason: T, retry_after_secs: u64) -> Self {
+		Self {
+			status: StatusCode::TOO_MANY_REQUESTS,
+			message: reason.into(),
+			retry_after: Some(retry_after_secs),
+		}
+	}
+
+	/// Classifies a `sqlx::Error` into a user-facing response: known constraint violations become
+	/// actionable [`Self::bad_request`]s, and everything else falls back to [`Self::internal`].
+	pub fn from_db(error: sqlx::Error) -> Self {
+		let sqlx::Error::Database(db_error) = &error else {
+			return Self::internal(error);
+		};
+
+		if db_error.is_unique_violation()
+			|| db_error.is_foreign_key_violation()
+			|| db_error.is_check_violation()
+		{
 			let message = db_error.message();
 			if let Some((_, constraint_name)) = message.split_once("constraint failed: ") {
 				if let Some(message) = constraint_message(constraint_name) {
 					return Self::bad_request(message);
 				}
 			}
+
+			if db_error.is_unique_violation() {
+				return Self::bad_request("That value is already in use.");
+			}
+			if db_error.is_foreign_key_violation() {
+				return Self::bad_request("That action refers to something that doesn't exist.");
+			}
 		}
 
 		Self::internal(error)
@@ -60,6 +88,7 @@ impl ErrorResponse {
 		ErrorResponse {
 			status: StatusCode::BAD_REQUEST,
 			message: reason.into(),
+			retry_after: None,
 		}
 	}
 
@@ -76,6 +105,7 @@ impl ErrorResponse {
 		ErrorResponse {
 			status: StatusCode::NOT_FOUND,
 			message,
+			retry_after: None,
 		}
 	}
 
@@ -88,17 +118,45 @@ impl ErrorResponse {
 			h1 { (self.status.as_str()) " " (mnemonic) }
 			p.preserve-space { (&self.message) }
 		};
-		(self.status, page("Error!", user, &body).custom_title()).into_response()
+		let retry_after = self.retry_after;
+		let mut response =
+			(self.status, page("Error!", user, &body).custom_title()).into_response();
+		if let Some(seconds) = retry_after {
+			response
+				.headers_mut()
+				.insert("Retry-After", seconds.to_string().try_into().unwrap());
+		}
+		response
 	}
 
 	pub fn into_response_in_extractor(self, parts: &axum::http::request::Parts) -> Response {
 		let user = parts.extensions.get::<User>();
 		self.into_response(user)
 	}
+
+	/// Like [`Self::into_response`], but for the JSON API, which has no user-facing page chrome to render into.
+	pub fn into_json_response(self) -> Response {
+		let retry_after = self.retry_after;
+		let mut response = (
+			self.status,
+			axum::Json(serde_json::json!({ "error": self.message })),
+		)
+			.into_response();
+		if let Some(seconds) = retry_after {
+			response
+				.headers_mut()
+				.insert("Retry-After", seconds.to_string().try_into().unwrap());
+		}
+		response
+	}
+}
+
+pub fn from_db(user: Option<&User>) -> impl '_ + FnOnce(sqlx::Error) -> Response {
+	move |error| ErrorResponse::from_db(error).into_response(user)
 }
 
-pub fn sqlx(user: Option<&User>) -> impl '_ + FnOnce(sqlx::Error) -> Response {
-	move |error| ErrorResponse::sqlx(error).into_response(user)
+pub fn internal<E: std::fmt::Debug>(user: Option<&User>) -> impl '_ + FnOnce(E) -> Response {
+	move |error| ErrorResponse::internal(error).into_response(user)
 }
 
 pub async fn not_found(user: Option<&User>) -> Response {
@@ -136,6 +194,7 @@ async fn method_not_allowed_layer_inner(req: Request<Body>, next: Next<Body>) ->
 		let error = ErrorResponse {
 			status: StatusCode::METHOD_NOT_ALLOWED,
 			message: format!("The {method} method is not supported for this route."),
+			retry_after: None,
 		};
 		// The default handler sets `Content-Length` manually (not sure why).
 		// This will be a problem because obviously it will not be correct.
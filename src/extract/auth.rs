@@ -47,6 +47,13 @@ impl Token {
 			.finish()
 	}
 
+	// Don't want to copy around this large array when there's no reason to.
+	#[allow(clippy::wrong_self_convention)]
+	#[allow(clippy::needless_borrow)]
+	pub fn to_hex(&self) -> String {
+		hex::encode(&self.0)
+	}
+
 	pub fn removal() -> impl IntoResponseParts {
 		struct Helper;
 
@@ -145,6 +152,10 @@ pub struct User {
 	pub id: UserId,
 	pub display_name: Arc<str>,
 	pub permission_level: PermissionLevel,
+	/// The session's anti-CSRF token (see `extract::csrf`), checked against the `_csrf` form field
+	/// on state-changing requests. Empty for API-token auth, which isn't cookie-based and so isn't
+	/// subject to CSRF in the first place.
+	pub csrf_token: Arc<str>,
 }
 
 impl User {
@@ -162,10 +173,48 @@ struct NoUser {
 	should_remove_token: bool,
 }
 
+fn extract_bearer_token(headers: &HeaderMap) -> Option<&str> {
+	headers
+		.get(axum::http::header::AUTHORIZATION)?
+		.to_str()
+		.ok()?
+		.strip_prefix("Bearer ")
+}
+
+/// Verifies the signature on `raw_token` and, if it's still valid (i.e. not revoked), looks up its owner.
+async fn extract_api_user(raw_token: &str, state: &State) -> Result<Option<User>, ErrorResponse> {
+	let Some(decoded) = crate::api_token::decode(&state.api_token_secret, raw_token) else {
+		return Ok(None);
+	};
+
+	let row = query!(
+		r#"select users.id as "id: UserId", users.display_name as "display_name: Arc<str>", users.permission_level as "permission_level!: PermissionLevel" from api_tokens inner join users on api_tokens.user = users.id where api_tokens.key_id = ? and api_tokens.user = ?"#,
+		decoded.key_id,
+		decoded.user,
+	)
+	.fetch_optional(&state.database)
+	.await
+	.map_err(ErrorResponse::from_db)?;
+
+	Ok(row.map(|row| User {
+		id: row.id,
+		display_name: row.display_name,
+		permission_level: row.permission_level,
+		csrf_token: Arc::from(""),
+	}))
+}
+
 async fn extract_user(
 	headers: &HeaderMap,
 	state: &State,
 ) -> Result<Result<User, NoUser>, ErrorResponse> {
+	if let Some(raw_token) = extract_bearer_token(headers) {
+		let user = extract_api_user(raw_token, state).await?;
+		return Ok(user.ok_or(NoUser {
+			should_remove_token: false,
+		}));
+	}
+
 	fn extract_cookie(headers: &HeaderMap) -> Result<Token, NoUser> {
 		headers
 			.get("Cookie")
@@ -191,7 +240,7 @@ async fn extract_user(
 		Err(error) => return Ok(Err(error)),
 	};
 
-	let Some(inner) = query!(r#"select user as id, users.display_name as "display_name: Arc<str>", users.permission_level as "permission_level!: PermissionLevel", expiration as "expiration: Timestamp" from sessions inner join users on sessions.user = users.id where token = ?"#, token).fetch_optional(&state.database).await.map_err(ErrorResponse::sqlx)? else { return Ok(Err(NoUser { should_remove_token: true })); };
+	let Some(inner) = query!(r#"select user as id, users.display_name as "display_name: Arc<str>", users.permission_level as "permission_level!: PermissionLevel", expiration as "expiration: Timestamp", csrf_token as "csrf_token!: Arc<str>" from sessions inner join users on sessions.user = users.id where token = ?"#, token).fetch_optional(&state.database).await.map_err(ErrorResponse::from_db)? else { return Ok(Err(NoUser { should_remove_token: true })); };
 
 	let now = now();
 
@@ -199,7 +248,7 @@ async fn extract_user(
 		query!("delete from sessions where token = ?", token)
 			.execute(&state.database)
 			.await
-			.map_err(ErrorResponse::sqlx)?;
+			.map_err(ErrorResponse::from_db)?;
 		return Ok(Err(NoUser {
 			should_remove_token: true,
 		}));
@@ -215,13 +264,14 @@ async fn extract_user(
 		)
 		.execute(&state.database)
 		.await
-		.map_err(ErrorResponse::sqlx)?;
+		.map_err(ErrorResponse::from_db)?;
 	}
 
 	Ok(Ok(User {
 		id: inner.id,
 		display_name: inner.display_name,
 		permission_level: inner.permission_level,
+		csrf_token: inner.csrf_token,
 	}))
 }
 
@@ -273,16 +323,52 @@ impl<S: Send> FromRequestParts<S> for User {
 
 pub const TOKEN_DURATION: Duration = days(5);
 const TOKEN_DURATION_GRANULARITY: Duration = minutes(5);
+const USER_AGENT_MAX_LEN: usize = 256;
+
+/// Truncated so a hostile client can't stuff an unbounded `User-Agent` into the `sessions` table.
+fn truncated_user_agent(headers: &HeaderMap) -> Option<String> {
+	let mut user_agent = headers.get(axum::http::header::USER_AGENT)?.to_str().ok()?.to_owned();
+	if user_agent.len() > USER_AGENT_MAX_LEN {
+		user_agent.truncate(USER_AGENT_MAX_LEN);
+	}
+	Some(user_agent)
+}
+
+/// Assumes the app sits behind a reverse proxy that sets this header; there's no other
+/// way to learn the client's address since we also listen on a Unix socket.
+pub(crate) fn client_ip(headers: &HeaderMap) -> Option<String> {
+	let raw = headers.get("X-Forwarded-For")?.to_str().ok()?;
+	Some(raw.split(',').next().unwrap_or(raw).trim().to_owned())
+}
+
+/// Generates a fresh token for a new session's anti-CSRF field. A new one is minted every time a
+/// session is created, so logging in (or out, which just deletes the session row) rotates it.
+fn generate_csrf_token() -> String {
+	hex::encode(rand::random::<[u8; 32]>())
+}
+
+pub async fn log_in(
+	state: &State,
+	user_id: UserId,
+	headers: &HeaderMap,
+) -> Result<Token, ErrorResponse> {
+	let creation_time = now();
+	let user_agent = truncated_user_agent(headers);
+	let client_ip = client_ip(headers);
+	let csrf_token = generate_csrf_token();
 
-pub async fn log_in(state: &State, user_id: UserId) -> Result<Token, ErrorResponse> {
 	let token = loop {
 		let token = Token::generate();
-		let expiration = now() + TOKEN_DURATION;
+		let expiration = creation_time + TOKEN_DURATION;
 		let res = query!(
-			"insert into sessions (token, user, expiration) values (?, ?, ?)",
+			"insert into sessions (token, user, expiration, creation_time, user_agent, client_ip, csrf_token) values (?, ?, ?, ?, ?, ?, ?)",
 			token,
 			user_id,
 			expiration,
+			creation_time,
+			user_agent,
+			client_ip,
+			csrf_token,
 		)
 		.execute(&state.database)
 		.await;
@@ -292,13 +378,29 @@ pub async fn log_in(state: &State, user_id: UserId) -> Result<Token, ErrorRespon
 			{
 				continue;
 			}
-			Err(error) => return Err(ErrorResponse::sqlx(error)),
+			Err(error) => return Err(ErrorResponse::from_db(error)),
 			Ok(_) => break token,
 		}
 	};
 	Ok(token)
 }
 
+/// Like [`User`], but for the JSON API: accepts either the session cookie or an `Authorization: Bearer`
+/// API token (both are resolved into the same [`User`] extension by [`layer_inner`]), and on failure
+/// returns a JSON [`ErrorResponse`] instead of redirecting to the login page.
+pub struct ApiUser(pub User);
+
+#[async_trait]
+impl<S: Send> FromRequestParts<S> for ApiUser {
+	type Rejection = Response;
+
+	async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Response> {
+		parts.extensions.get::<User>().cloned().map(Self).ok_or_else(|| {
+			ErrorResponse::bad_request("You must be logged in to use the API.").into_json_response()
+		})
+	}
+}
+
 macro_rules! permission_extractor {
 	($name:ident) => {
 		pub struct $name(pub User);
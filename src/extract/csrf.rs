@@ -0,0 +1,87 @@
+use axum::body::Body;
+use axum::extract::FromRequest;
+use axum::http::{header, Method, Request, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::async_trait;
+use maud::html;
+
+use crate::error::ErrorResponse;
+use crate::extract::auth::User;
+use crate::template::{page, BannerKind};
+use crate::util::constant_time_eq;
+
+/// Wraps another body extractor `T`, checking the submitted `_csrf` token against the current
+/// session's token (see [`User::csrf_token`]) before letting `T` consume the body. Sibling of
+/// [`super::if_post::IfPost`]: only POSTs are checked, since GETs can't mutate state and so aren't
+/// a forgery target. Login/registration, which happen before a session exists, are out of scope
+/// for this guard; wrap the body extractor of any other authenticated POST handler with this to
+/// protect it.
+///
+/// For `application/x-www-form-urlencoded` bodies (the common case: `extract::Form`), the token
+/// is read out of a `_csrf` field, added to the form with [`crate::template::csrf_field`]. Other
+/// bodies (namely `multipart/form-data`, used by file uploads) aren't buffered here since the
+/// wrapped extractor needs to stream them itself; for those the token instead travels on the
+/// query string, added to the form's `action` with [`crate::template::csrf_query`].
+pub struct Csrf<T>(pub T);
+
+#[async_trait]
+impl<S, T> FromRequest<S, Body> for Csrf<T>
+where
+	S: Send + Sync,
+	T: FromRequest<S, Body>,
+	T::Rejection: IntoResponse,
+{
+	type Rejection = Response;
+
+	async fn from_request(request: Request<Body>, state: &S) -> Result<Self, Self::Rejection> {
+		if request.method() != Method::POST {
+			return T::from_request(request, state)
+				.await
+				.map(Self)
+				.map_err(IntoResponse::into_response);
+		}
+
+		let user = request.extensions().get::<User>().cloned();
+
+		let is_urlencoded = request
+			.headers()
+			.get(header::CONTENT_TYPE)
+			.and_then(|value| value.to_str().ok())
+			.is_some_and(|value| value.starts_with("application/x-www-form-urlencoded"));
+
+		let (submitted, request) = if is_urlencoded {
+			let (parts, body) = request.into_parts();
+			let bytes = hyper::body::to_bytes(body).await.map_err(|error| {
+				ErrorResponse::bad_request(format!("Failed to read the request body: {error}"))
+					.into_response(user.as_ref())
+			})?;
+			let submitted = serde_urlencoded::from_bytes::<Vec<(String, String)>>(&bytes)
+				.ok()
+				.and_then(|fields| fields.into_iter().find(|(name, _)| name == "_csrf").map(|(_, value)| value))
+				.unwrap_or_default();
+			(submitted, Request::from_parts(parts, Body::from(bytes)))
+		} else {
+			let submitted = request
+				.uri()
+				.query()
+				.and_then(|query| serde_urlencoded::from_str::<Vec<(String, String)>>(query).ok())
+				.and_then(|fields| fields.into_iter().find(|(name, _)| name == "_csrf").map(|(_, value)| value))
+				.unwrap_or_default();
+			(submitted, request)
+		};
+
+		let expected = user.as_ref().map_or("", |user| &*user.csrf_token);
+		if expected.is_empty() || !constant_time_eq(submitted.as_bytes(), expected.as_bytes()) {
+			let body = html! { p { "This form could not be verified and may be stale. Please reload the page and try again." } };
+			return Err((
+				StatusCode::FORBIDDEN,
+				page("Error!", user.as_ref(), &body)
+					.with_banner(BannerKind::Error, "Invalid or missing CSRF token.")
+					.custom_title(),
+			)
+				.into_response());
+		}
+
+		T::from_request(request, state).await.map(Self).map_err(IntoResponse::into_response)
+	}
+}
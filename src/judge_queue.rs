@@ -0,0 +1,64 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::{mpsc, Mutex};
+
+use crate::model::SubmissionId;
+use crate::State;
+
+/// Bound on how many queued-but-not-yet-picked-up submissions can pile up before `enqueue` backpressures.
+const CHANNEL_CAPACITY: usize = 256;
+
+pub struct JudgeQueue {
+	sender: mpsc::Sender<SubmissionId>,
+	depth: AtomicUsize,
+}
+
+impl JudgeQueue {
+	pub fn new() -> (Self, mpsc::Receiver<SubmissionId>) {
+		let (sender, receiver) = mpsc::channel(CHANNEL_CAPACITY);
+		let this = Self {
+			sender,
+			depth: AtomicUsize::new(0),
+		};
+		(this, receiver)
+	}
+
+	/// Enqueues `submission_id` for background judging.
+	/// Fails only if the worker pool has shut down, which should not happen while the server is running.
+	pub async fn enqueue(&self, submission_id: SubmissionId) -> Result<(), mpsc::error::SendError<SubmissionId>> {
+		self.sender.send(submission_id).await?;
+		self.depth.fetch_add(1, Ordering::Relaxed);
+		Ok(())
+	}
+
+	pub fn depth(&self) -> usize {
+		self.depth.load(Ordering::Relaxed)
+	}
+}
+
+/// Spawns `num_workers` tasks that pull submission ids from `receiver` and judge them one at a time.
+pub fn spawn_workers(state: Arc<State>, receiver: mpsc::Receiver<SubmissionId>, num_workers: u32) {
+	let receiver = Arc::new(Mutex::new(receiver));
+
+	for _ in 0..num_workers {
+		let receiver = Arc::clone(&receiver);
+		let state = Arc::clone(&state);
+		tokio::spawn(async move {
+			loop {
+				let submission_id = {
+					let mut receiver = receiver.lock().await;
+					receiver.recv().await
+				};
+				let Some(submission_id) = submission_id else {
+					break;
+				};
+				state.judge_queue.depth.fetch_sub(1, Ordering::Relaxed);
+
+				if let Err(error) = crate::routes::submissions::do_judge(&state, submission_id).await {
+					tracing::error!(?submission_id, message = %error.message, "error judging submission");
+				}
+			}
+		});
+	}
+}
@@ -31,23 +31,42 @@ use crate::resources::resources;
 use crate::sandbox::Sandbox;
 use crate::time::now;
 
+mod api_token;
+mod blob_store;
 mod config;
 mod error;
 mod extract;
+mod judge_queue;
+mod mod_log;
 mod model;
 mod password;
+mod ratelimit;
 mod resources;
 mod routes;
 mod sandbox;
 mod template;
 mod time;
+mod totp;
 mod util;
 
 static CONFIG: Lazy<Config> = Lazy::new(Config::load);
 
+const DATABASE_PATH: &str = "db.sqlite";
+/// Where the admin diagnostics console stages an uploaded backup; applied on the next startup
+/// rather than swapping the live database file out from under the running pool.
+pub(crate) const RESTORE_PENDING_PATH: &str = "db.sqlite.restore-pending";
+
 pub struct State {
 	database: SqlitePool,
 	sandbox: Sandbox,
+	ratelimit: ratelimit::RateLimiter,
+	login_limiter: ratelimit::LoginLimiter,
+	sql_ratelimit: ratelimit::ClientRateLimiter,
+	auth_ratelimit: ratelimit::ClientRateLimiter,
+	judge_queue: judge_queue::JudgeQueue,
+	blobs: Box<dyn blob_store::BlobStore>,
+	/// Loaded from `CONFIG.api_token_secret` so outstanding API tokens keep verifying across restarts.
+	api_token_secret: api_token::Secret,
 }
 
 #[tokio::main(flavor = "current_thread")]
@@ -67,8 +86,10 @@ async fn main() {
 
 	Lazy::force(&CONFIG);
 
+	apply_pending_restore();
+
 	let db_options = SqliteConnectOptions::new()
-		.filename("db.sqlite")
+		.filename(DATABASE_PATH)
 		.journal_mode(SqliteJournalMode::Wal)
 		.synchronous(SqliteSynchronous::Normal)
 		.create_if_missing(true);
@@ -94,10 +115,50 @@ async fn main() {
 
 	let sandbox = sandbox::Sandbox::new().await;
 
-	let state = Arc::new(State { database, sandbox });
+	let ratelimit = ratelimit::RateLimiter::new(
+		CONFIG.submission_rate_limit.capacity,
+		std::time::Duration::from_secs(CONFIG.submission_rate_limit.window_seconds),
+	);
+	let sql_ratelimit = ratelimit::ClientRateLimiter::new(
+		CONFIG.sql_rate_limit.capacity,
+		std::time::Duration::from_secs(CONFIG.sql_rate_limit.window_seconds),
+	);
+	let auth_ratelimit = ratelimit::ClientRateLimiter::new(
+		CONFIG.auth_rate_limit.capacity,
+		std::time::Duration::from_secs(CONFIG.auth_rate_limit.window_seconds),
+	);
+
+	let (judge_queue, judge_receiver) = judge_queue::JudgeQueue::new();
+
+	let blobs: Box<dyn blob_store::BlobStore> = match &CONFIG.blob_storage {
+		config::BlobStorage::Sqlite => Box::new(blob_store::SqliteBlobStore::new(database.clone())),
+		config::BlobStorage::Http(http_config) => {
+			Box::new(blob_store::HttpBlobStore::new(http_config))
+		}
+	};
+
+	let mut api_token_secret: api_token::Secret = [0; 32];
+	hex::decode_to_slice(&CONFIG.api_token_secret, &mut api_token_secret)
+		.expect("api_token_secret must be 64 hex characters");
+
+	let state = Arc::new(State {
+		database,
+		sandbox,
+		ratelimit,
+		login_limiter: ratelimit::LoginLimiter::default(),
+		sql_ratelimit,
+		auth_ratelimit,
+		judge_queue,
+		blobs,
+		api_token_secret,
+	});
+
+	tokio::spawn(prune_rate_limiter(Arc::clone(&state)));
+	tokio::spawn(prune_login_limiter(Arc::clone(&state)));
+	judge_queue::spawn_workers(Arc::clone(&state), judge_receiver, CONFIG.judge_workers);
 
 	let app = axum::Router::new()
-		.merge(routes::router().layer(error::method_not_allowed_layer()))
+		.merge(routes::router(Arc::clone(&state)).layer(error::method_not_allowed_layer()))
 		.fallback(error::not_found_handler)
 		.layer(auth::layer(Arc::clone(&state)))
 		.with_state(state)
@@ -122,6 +183,19 @@ async fn main() {
 	.expect("running server");
 }
 
+/// If the admin diagnostics console staged a restore before the last shutdown, apply it now,
+/// before anything else opens the database file.
+fn apply_pending_restore() {
+	if !std::path::Path::new(RESTORE_PENDING_PATH).exists() {
+		return;
+	}
+	tracing::info!("applying pending database restore");
+	_ = std::fs::remove_file(format!("{DATABASE_PATH}-wal"));
+	_ = std::fs::remove_file(format!("{DATABASE_PATH}-shm"));
+	std::fs::rename(RESTORE_PENDING_PATH, DATABASE_PATH)
+		.expect("applying pending database restore");
+}
+
 async fn clear_expired_tokens(database: SqlitePool) {
 	let mut interval = tokio::time::interval(std::time::Duration::from_secs(60));
 	interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
@@ -139,3 +213,31 @@ async fn clear_expired_tokens(database: SqlitePool) {
 		}
 	}
 }
+
+async fn prune_rate_limiter(state: Arc<State>) {
+	const IDLE_FOR: std::time::Duration = std::time::Duration::from_secs(60 * 10);
+
+	let mut interval = tokio::time::interval(IDLE_FOR);
+	interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+	loop {
+		interval.tick().await;
+		tracing::debug!("pruning idle rate limit buckets");
+		state.ratelimit.prune(IDLE_FOR);
+		state.sql_ratelimit.prune(IDLE_FOR);
+		state.auth_ratelimit.prune(IDLE_FOR);
+	}
+}
+
+async fn prune_login_limiter(state: Arc<State>) {
+	const IDLE_FOR: std::time::Duration = std::time::Duration::from_secs(60 * 10);
+
+	let mut interval = tokio::time::interval(IDLE_FOR);
+	interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+	loop {
+		interval.tick().await;
+		tracing::debug!("pruning idle login attempt records");
+		state.login_limiter.prune(IDLE_FOR);
+	}
+}
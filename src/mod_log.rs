@@ -0,0 +1,76 @@
+use sqlx::query;
+
+use crate::model::{Id, UserId};
+use crate::time::now;
+use crate::util::db_enum;
+use crate::State;
+
+db_enum! {
+pub enum Action {
+	EditProblem = 0,
+	DeleteProblem = 1,
+	DeleteSubmission = 2,
+	RejudgeSubmission = 3,
+	DownloadCases = 4,
+	DownloadBackup = 5,
+	RestoreBackup = 6,
+}
+}
+
+impl Action {
+	pub fn name(self) -> &'static str {
+		match self {
+			Self::EditProblem => "Edit problem",
+			Self::DeleteProblem => "Delete problem",
+			Self::DeleteSubmission => "Delete submission",
+			Self::RejudgeSubmission => "Rejudge submission",
+			Self::DownloadCases => "Download cases",
+			Self::DownloadBackup => "Download database backup",
+			Self::RestoreBackup => "Restore database from backup",
+		}
+	}
+}
+
+db_enum! {
+pub enum TargetType {
+	Problem = 0,
+	Submission = 1,
+	/// For actions that affect the whole instance rather than one row, e.g. database backup/restore.
+	System = 2,
+}
+}
+
+impl TargetType {
+	pub fn name(self) -> &'static str {
+		match self {
+			Self::Problem => "Problem",
+			Self::Submission => "Submission",
+			Self::System => "System",
+		}
+	}
+}
+
+/// Records a privileged action for later review on `/modlog`.
+/// `detail` should be a serialized JSON object if present, but is stored opaquely.
+pub async fn record(
+	state: &State,
+	actor: UserId,
+	action: Action,
+	target_type: TargetType,
+	target_id: Id,
+	detail: Option<&str>,
+) -> Result<(), sqlx::Error> {
+	let time = now();
+	query!(
+		"insert into mod_log (actor, action, target_type, target_id, detail, time) values (?, ?, ?, ?, ?, ?)",
+		actor,
+		action,
+		target_type,
+		target_id,
+		detail,
+		time,
+	)
+	.execute(&state.database)
+	.await?;
+	Ok(())
+}
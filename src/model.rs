@@ -31,6 +31,22 @@ impl PermissionLevel {
 	}
 }
 
+db_enum! {
+pub enum JudgeStatus {
+	Queued = 0,
+	Judging = 1,
+}
+}
+
+impl JudgeStatus {
+	pub fn name(self) -> &'static str {
+		match self {
+			Self::Queued => "Queued",
+			Self::Judging => "Judging",
+		}
+	}
+}
+
 db_enum! {
 pub enum Language {
 	Python3 = 0,
@@ -53,6 +69,13 @@ impl Language {
 	}
 }
 
+/// Round-trips with [`std::str::FromStr`] (via [`db_enum`]'s repr-based parsing), for use in query strings.
+impl std::fmt::Display for Language {
+	fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(formatter, "{}", self.repr())
+	}
+}
+
 pub struct Tests {
 	inner: String,
 }
@@ -88,6 +111,20 @@ impl Tests {
 	}
 }
 
+/// Inverse of [`Tests::cases`]: joins `(input, output)` pairs back into the raw format `Tests` parses.
+pub fn join_cases<'a>(cases: impl IntoIterator<Item = (&'a str, &'a str)>) -> String {
+	let mut buf = String::new();
+	for (i, (input, output)) in cases.into_iter().enumerate() {
+		if i > 0 {
+			buf.push_str(TEST_CASE_SEPARATOR);
+		}
+		buf.push_str(input);
+		buf.push_str(TEST_IN_OUT_SEPARATOR);
+		buf.push_str(output);
+	}
+	buf
+}
+
 impl Debug for Tests {
 	fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
 		struct Helper<'a>(&'a str);
@@ -222,7 +259,8 @@ impl TryFrom<String> for crate::sandbox::TestResponse {
 	}
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum SimpleTestResponse {
 	Correct,
 	Wrong,
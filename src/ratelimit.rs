@@ -0,0 +1,296 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::body::Body;
+use axum::http::{Method, Request};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::routing::Route;
+use axum::{extract, middleware};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::error::ErrorResponse;
+use crate::extract::auth::{client_ip, User};
+use crate::model::UserId;
+use crate::util::s;
+use crate::State;
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+	/// Number of tokens currently available, as a float so that fractional refills accumulate correctly.
+	remaining: f64,
+	last_refill: Instant,
+}
+
+#[derive(Debug)]
+pub enum Decision {
+	Allowed,
+	Denied { retry_after: Duration },
+}
+
+/// A keyed token bucket: each [`UserId`] gets its own bucket that refills at a constant rate up to `capacity`.
+pub struct RateLimiter {
+	buckets: Mutex<HashMap<UserId, Bucket>>,
+	capacity: f64,
+	refill_per_second: f64,
+}
+
+impl RateLimiter {
+	pub fn new(capacity: u32, window: Duration) -> Self {
+		Self {
+			buckets: Mutex::new(HashMap::new()),
+			capacity: f64::from(capacity),
+			refill_per_second: f64::from(capacity) / window.as_secs_f64(),
+		}
+	}
+
+	/// Takes a token for `user` if one is available, refilling first based on elapsed time.
+	pub fn check(&self, user: UserId) -> Decision {
+		let now = Instant::now();
+		let mut buckets = self.buckets.lock().unwrap();
+		let bucket = buckets.entry(user).or_insert(Bucket {
+			remaining: self.capacity,
+			last_refill: now,
+		});
+
+		let elapsed = now.saturating_duration_since(bucket.last_refill).as_secs_f64();
+		bucket.remaining = (bucket.remaining + elapsed * self.refill_per_second).min(self.capacity);
+		bucket.last_refill = now;
+
+		if bucket.remaining >= 1.0 {
+			bucket.remaining -= 1.0;
+			Decision::Allowed
+		} else {
+			let missing = 1.0 - bucket.remaining;
+			let seconds = missing / self.refill_per_second;
+			Decision::Denied {
+				retry_after: Duration::from_secs_f64(seconds.max(0.0)),
+			}
+		}
+	}
+
+	/// Drops buckets that are both full and haven't been touched in a while, so the map doesn't grow unbounded.
+	pub fn prune(&self, idle_for: Duration) {
+		let now = Instant::now();
+		let mut buckets = self.buckets.lock().unwrap();
+		buckets.retain(|_, bucket| {
+			bucket.remaining < self.capacity
+				|| now.saturating_duration_since(bucket.last_refill) < idle_for
+		});
+	}
+}
+
+#[derive(Debug, Clone, Copy)]
+struct LoginAttempts {
+	failures: u32,
+	blocked_until: Option<Instant>,
+	last_attempt: Instant,
+}
+
+/// Tracks failed login attempts per arbitrary string key (e.g. `user:<username>` or `ip:<address>`)
+/// and imposes an exponentially increasing lockout once a key accumulates too many failures.
+#[derive(Default)]
+pub struct LoginLimiter {
+	attempts: Mutex<HashMap<String, LoginAttempts>>,
+}
+
+impl LoginLimiter {
+	const FAILURE_THRESHOLD: u32 = 5;
+	const BASE_BACKOFF: Duration = Duration::from_secs(1);
+	const MAX_BACKOFF: Duration = Duration::from_secs(15 * 60);
+
+	/// Returns the remaining lockout duration if `key` is currently locked out.
+	pub fn check(&self, key: &str) -> Result<(), Duration> {
+		let now = Instant::now();
+		let attempts = self.attempts.lock().unwrap();
+		match attempts.get(key).and_then(|entry| entry.blocked_until) {
+			Some(blocked_until) if blocked_until > now => Err(blocked_until - now),
+			_ => Ok(()),
+		}
+	}
+
+	/// Records a failed attempt for `key`, extending its lockout once it crosses [`Self::FAILURE_THRESHOLD`].
+	pub fn record_failure(&self, key: &str) {
+		let now = Instant::now();
+		let mut attempts = self.attempts.lock().unwrap();
+		let entry = attempts.entry(key.to_owned()).or_insert(LoginAttempts {
+			failures: 0,
+			blocked_until: None,
+			last_attempt: now,
+		});
+		entry.failures += 1;
+		entry.last_attempt = now;
+
+		if entry.failures >= Self::FAILURE_THRESHOLD {
+			let extra_failures = entry.failures - Self::FAILURE_THRESHOLD;
+			let backoff = Self::BASE_BACKOFF
+				.saturating_mul(1 << extra_failures.min(20))
+				.min(Self::MAX_BACKOFF);
+			entry.blocked_until = Some(now + backoff);
+		}
+	}
+
+	/// Clears `key`'s failure history after a successful login.
+	pub fn record_success(&self, key: &str) {
+		self.attempts.lock().unwrap().remove(key);
+	}
+
+	/// Drops entries that are neither locked out nor recently active, so the map doesn't grow unbounded.
+	pub fn prune(&self, idle_for: Duration) {
+		let now = Instant::now();
+		let mut attempts = self.attempts.lock().unwrap();
+		attempts.retain(|_, entry| {
+			entry.blocked_until.is_some_and(|blocked_until| blocked_until > now)
+				|| now.saturating_duration_since(entry.last_attempt) < idle_for
+		});
+	}
+}
+
+/// A keyed token bucket like [`RateLimiter`], but keyed by an arbitrary client identity string
+/// (see [`client_key`]) instead of a [`UserId`], for routes that can be hit by clients who aren't
+/// logged in yet.
+pub struct ClientRateLimiter {
+	buckets: Mutex<HashMap<String, Bucket>>,
+	capacity: f64,
+	refill_per_second: f64,
+}
+
+impl ClientRateLimiter {
+	pub fn new(capacity: u32, window: Duration) -> Self {
+		Self {
+			buckets: Mutex::new(HashMap::new()),
+			capacity: f64::from(capacity),
+			refill_per_second: f64::from(capacity) / window.as_secs_f64(),
+		}
+	}
+
+	/// Takes a token for `key` if one is available, refilling first based on elapsed time.
+	fn check(&self, key: &str) -> Decision {
+		let now = Instant::now();
+		let mut buckets = self.buckets.lock().unwrap();
+		let bucket = buckets.entry(key.to_owned()).or_insert(Bucket {
+			remaining: self.capacity,
+			last_refill: now,
+		});
+
+		let elapsed = now.saturating_duration_since(bucket.last_refill).as_secs_f64();
+		bucket.remaining = (bucket.remaining + elapsed * self.refill_per_second).min(self.capacity);
+		bucket.last_refill = now;
+
+		if bucket.remaining >= 1.0 {
+			bucket.remaining -= 1.0;
+			Decision::Allowed
+		} else {
+			let missing = 1.0 - bucket.remaining;
+			let seconds = missing / self.refill_per_second;
+			Decision::Denied {
+				retry_after: Duration::from_secs_f64(seconds.max(0.0)),
+			}
+		}
+	}
+
+	/// Drops buckets that are both full and haven't been touched in a while, so the map doesn't grow unbounded.
+	pub fn prune(&self, idle_for: Duration) {
+		let now = Instant::now();
+		let mut buckets = self.buckets.lock().unwrap();
+		buckets.retain(|_, bucket| {
+			bucket.remaining < self.capacity
+				|| now.saturating_duration_since(bucket.last_refill) < idle_for
+		});
+	}
+}
+
+/// Which configurable [`ClientRateLimiter`] in [`State`] a [`layer`]ed route draws from, and what
+/// to tell the client it's being limited on.
+#[derive(Debug, Clone, Copy)]
+pub enum RouteClass {
+	/// The admin SQL console, which lets an admin run arbitrary queries against the database.
+	Sql,
+	/// Logging in and registering, which are reachable by clients who aren't authenticated yet.
+	Auth,
+}
+
+impl RouteClass {
+	fn limiter(self, state: &State) -> &ClientRateLimiter {
+		match self {
+			Self::Sql => &state.sql_ratelimit,
+			Self::Auth => &state.auth_ratelimit,
+		}
+	}
+
+	fn description(self) -> &'static str {
+		match self {
+			Self::Sql => "the SQL console",
+			Self::Auth => "logging in or registering",
+		}
+	}
+}
+
+/// Identifies the client for [`ClientRateLimiter`] purposes: the authenticated user if there is
+/// one (set on the request by [`crate::extract::auth::layer`], which runs before this layer),
+/// else their peer IP. Clients with neither (no session and no forwarded-for header) share a
+/// single bucket rather than bypassing the limit.
+fn client_key(request: &Request<Body>) -> String {
+	if let Some(user) = request.extensions().get::<User>() {
+		format!("user:{}", user.id)
+	} else if let Some(ip) = client_ip(request.headers()) {
+		format!("ip:{ip}")
+	} else {
+		"unknown".to_owned()
+	}
+}
+
+#[derive(Clone)]
+struct LayerState {
+	state: Arc<State>,
+	class: RouteClass,
+}
+
+async fn layer_inner(
+	extract::State(LayerState { state, class }): extract::State<LayerState>,
+	request: Request<Body>,
+	next: Next<Body>,
+) -> Response {
+	// Only POSTs actually attempt a login/query/etc; GETs are just page loads and shouldn't draw
+	// from the same bucket, or reloading the page a few times could lock a client out on its own.
+	if request.method() != Method::POST {
+		return next.run(request).await;
+	}
+
+	let user = request.extensions().get::<User>().cloned();
+	let key = client_key(&request);
+
+	match class.limiter(&state).check(&key) {
+		Decision::Allowed => next.run(request).await,
+		Decision::Denied { retry_after } => {
+			let seconds = retry_after.as_secs().max(1);
+			ErrorResponse::too_many_requests(
+				format!(
+					"Too many requests to {}. Please wait {seconds} second{} and try again.",
+					class.description(),
+					s(seconds.try_into().unwrap_or(i64::MAX)),
+				),
+				seconds,
+			)
+			.into_response(user.as_ref())
+		}
+	}
+}
+
+/// Rate-limits a route by client identity (see [`client_key`]), drawing capacity/refill rate from
+/// `state`'s `class` limiter. Meant to be applied with `Router::route_layer` to just the routes
+/// that need it, not the whole app, since [`auth::layer`](crate::extract::auth::layer) already
+/// covers session handling for every route.
+#[rustfmt::skip] // Rustfmt chokes on this big generic type.
+pub fn layer(
+	state: Arc<State>,
+	class: RouteClass,
+) -> impl Layer<
+	Route,
+	Service = impl Service<Request<Body>, Response = Response, Future = impl Send, Error = Infallible> + Clone,
+> + Clone {
+	middleware::from_fn_with_state(LayerState { state, class }, layer_inner)
+}
@@ -5,26 +5,152 @@ use axum::extract;
 use axum::response::{IntoResponse, Response};
 use axum::routing::get;
 use futures_util::StreamExt as _;
-use maud::{html, html_into};
+use maud::{html, html_into, Markup};
 use serde::Deserialize;
-use sqlx::{query, query_scalar, Column, Either, Row, Type, TypeInfo, ValueRef};
+use sqlx::{query, query_as, query_scalar, Column, Either, Row, Type, TypeInfo, ValueRef};
 
-use crate::extract::auth::Admin;
+use crate::error::ErrorResponse;
+use crate::extract::auth::{Admin, User};
+use crate::extract::csrf::Csrf;
 use crate::extract::if_post::IfPost;
 use crate::extract::pagination::RawPagination;
-use crate::model::{Language, PermissionLevel, ProblemId, SimpleTestResponse};
-use crate::template::page;
-use crate::time::Timestamp;
-use crate::util::{render_debug, s};
-use crate::{error, State};
+use crate::model::{Language, PermissionLevel, ProblemId, SimpleTestResponse, SubmissionId, UserId};
+use crate::routes::submissions::VerdictFilter;
+use crate::template::{csrf_field, csrf_query, page};
+use crate::time::{now, Timestamp};
+use crate::util::{render_debug, s, search_query};
+use crate::{error, mod_log, ratelimit, State};
 
 const DEFAULT_PAGE_SIZE: u32 = 30;
 
-#[derive(serde::Deserialize)]
-struct SubmissionsSearch {
-	submitter: Option<String>,
-	problem: Option<String>,
-	problem_id: Option<String>,
+search_query! { struct SubmissionsSearch {
+	submitter: String,
+	problem: String,
+	problem_id: ProblemId,
+	language: Language,
+	result: VerdictFilter,
+	after: Timestamp,
+	before: Timestamp,
+	reverse: bool,
+} }
+
+/// A bound value for the dynamic predicates built in this module. Kept to the handful of
+/// primitive shapes the filters actually need, since the SQL text itself is already fully dynamic.
+enum SearchBind {
+	Str(String),
+	I64(i64),
+}
+
+/// Turns free-text `input` into an FTS5 `MATCH` query: each whitespace-separated term becomes a
+/// quoted prefix token, joined with `OR` so rows matching more terms rank higher. Returns `None`
+/// if `input` has no usable tokens (e.g. empty or all whitespace), in which case callers should
+/// fall back to a plain `instr()` scan.
+fn fts_query(input: &str) -> Option<String> {
+	let terms: Vec<String> = input
+		.split_whitespace()
+		.map(|term| format!("\"{}\"*", term.replace('"', "\"\"")))
+		.collect();
+	(!terms.is_empty()).then(|| terms.join(" OR "))
+}
+
+/// Builds the `where` clause and its bind values for the submissions search, so that the
+/// `count(*)` query and the row query apply exactly the same predicate. Unlike the rest of the
+/// codebase, this can't be a single `query!`-checked string: the set of active filters varies per
+/// request, so the clauses are accumulated at runtime and joined with `" and "`. Also returns
+/// `rank`-ordering expressions (and their binds) for any text filters that hit the FTS5 index, so
+/// the row query can surface the best matches first.
+fn build_predicate(
+	search: &SubmissionsSearch,
+) -> (String, Vec<SearchBind>, Vec<String>, Vec<SearchBind>) {
+	let mut clauses: Vec<&'static str> = Vec::new();
+	let mut binds: Vec<SearchBind> = Vec::new();
+	let mut order_exprs: Vec<String> = Vec::new();
+	let mut order_binds: Vec<SearchBind> = Vec::new();
+
+	if let Some(submitter) = &search.submitter {
+		if let Some(fts) = fts_query(submitter) {
+			clauses.push("submitter.id in (select rowid from users_fts where users_fts match ?)");
+			binds.push(SearchBind::Str(fts.clone()));
+			order_exprs.push(
+				"(select rank from users_fts where rowid = submitter.id and users_fts match ?)"
+					.to_owned(),
+			);
+			order_binds.push(SearchBind::Str(fts));
+		} else {
+			clauses.push("instr(submitter.display_name, ?) > 0");
+			binds.push(SearchBind::Str(submitter.clone()));
+		}
+	}
+	if let Some(problem) = &search.problem {
+		if let Some(fts) = fts_query(problem) {
+			clauses.push("problem.id in (select rowid from problems_fts where problems_fts match ?)");
+			binds.push(SearchBind::Str(fts.clone()));
+			order_exprs.push(
+				"(select rank from problems_fts where rowid = problem.id and problems_fts match ?)"
+					.to_owned(),
+			);
+			order_binds.push(SearchBind::Str(fts));
+		} else {
+			clauses.push("instr(problem.name, ?) > 0");
+			binds.push(SearchBind::Str(problem.clone()));
+		}
+	}
+	if let Some(problem_id) = search.problem_id {
+		clauses.push("submissions.for_problem = ?");
+		binds.push(SearchBind::I64(problem_id));
+	}
+	if let Some(language) = search.language {
+		clauses.push("submissions.language = ?");
+		binds.push(SearchBind::I64(language.repr()));
+	}
+	if let Some(result) = search.result {
+		clauses.push(match result {
+			VerdictFilter::Correct => "submissions.result like 'o%'",
+			VerdictFilter::Wrong => "(submissions.result is not null and submissions.result not like 'o%')",
+			VerdictFilter::NotJudged => "submissions.result is null",
+		});
+	}
+	if let Some(after) = search.after {
+		clauses.push("submissions.submission_time >= ?");
+		binds.push(SearchBind::I64(after.into()));
+	}
+	if let Some(before) = search.before {
+		clauses.push("submissions.submission_time <= ?");
+		binds.push(SearchBind::I64(before.into()));
+	}
+
+	let where_clause = if clauses.is_empty() {
+		"1".to_owned()
+	} else {
+		clauses.join(" and ")
+	};
+	(where_clause, binds, order_exprs, order_binds)
+}
+
+/// Applies `binds` to `query` in the same order they were pushed.
+macro_rules! apply_binds {
+	($query:expr, $binds:expr) => {{
+		let mut query = $query;
+		for bind in $binds {
+			query = match bind {
+				SearchBind::Str(value) => query.bind(value.clone()),
+				SearchBind::I64(value) => query.bind(*value),
+			};
+		}
+		query
+	}};
+}
+
+#[derive(sqlx::FromRow)]
+struct SubmissionRow {
+	submission_id: SubmissionId,
+	problem_id: ProblemId,
+	problem_name: String,
+	submitter_id: UserId,
+	submitter_name: String,
+	language: Language,
+	submission_time: Timestamp,
+	result: Option<SimpleTestResponse>,
 }
 
 async fn submissions(
@@ -33,34 +159,66 @@ async fn submissions(
 	pagination: RawPagination,
 	extract::Query(search): extract::Query<SubmissionsSearch>,
 ) -> Result<Response, Response> {
-	let search_submitter = search.submitter.filter(|s| !s.is_empty());
-	let search_problem = search.problem.filter(|s| !s.is_empty());
-	let search_problem_id = search.problem_id.and_then(|s| s.parse::<ProblemId>().ok());
-	let any_search =
-		search_submitter.is_some() || search_problem.is_some() || search_problem_id.is_some();
+	let any_search = search.any_set();
 
 	let pagination = pagination.with_default_page_size(DEFAULT_PAGE_SIZE);
 	let limit = pagination.limit();
 	let offset = pagination.offset();
 
-	let num_submissions = if any_search {
-		query_scalar!(r#"select count(*) as "count: i64" from submissions where (?1 is null or submissions.submitter in (select id from users where instr(display_name, ?1) > 0)) and (?2 is null or submissions.for_problem in (select id from problems where instr(name, ?2) > 0)) and (?3 is null or submissions.for_problem = ?3)"#, search_submitter, search_problem, search_problem_id)
-	} else {
-		query_scalar!(r#"select count(*) as "count: i64" from submissions"#)
-	}
+	let (where_clause, binds, order_exprs, order_binds) = build_predicate(&search);
+	const FROM: &str = "from submissions inner join problems as problem on submissions.for_problem = problem.id inner join users as submitter on submissions.submitter = submitter.id";
+
+	let count_sql = format!("select count(*) {FROM} where {where_clause}");
+	let num_submissions = apply_binds!(query_scalar::<_, i64>(&count_sql), &binds)
 		.fetch_one(&state.database)
 		.await
-		.map_err(error::internal(Some(&user)))?;
+		.map_err(error::from_db(Some(&user)))?;
 
-	let submissions = query!(r#"select submissions.id as submission_id, problems.id as problem_id, problems.name as problem_name, users.id as submitter_id, users.display_name as submitter_name, language as "language: Language", submission_time as "submission_time: Timestamp", result as "result: SimpleTestResponse" from submissions inner join problems on submissions.for_problem = problems.id inner join users on submissions.submitter = users.id where (?1 is null or submissions.submitter in (select id from users where instr(display_name, ?1) > 0)) and (?2 is null or submissions.for_problem in (select id from problems where instr(name, ?2) > 0)) and (?3 is null or submissions.for_problem = ?3) order by submissions.id desc limit ?4 offset ?5"#, search_submitter, search_problem, search_problem_id, limit, offset).fetch_all(&state.database).await.map_err(error::internal(Some(&user)))?;
+	let order = if search.reverse == Some(true) { "asc" } else { "desc" };
+	// When a text filter hit the FTS5 index, surface the best matches first; otherwise fall back
+	// to the plain chronological order.
+	let order_by = if order_exprs.is_empty() {
+		format!("submissions.id {order}")
+	} else {
+		format!("({}) asc, submissions.id {order}", order_exprs.join(" + "))
+	};
+	let rows_sql = format!(
+		"select submissions.id as submission_id, problem.id as problem_id, problem.name as problem_name, submitter.id as submitter_id, submitter.display_name as submitter_name, submissions.language, submissions.submission_time, submissions.result {FROM} where {where_clause} order by {order_by} limit ? offset ?"
+	);
+	let submissions = apply_binds!(query_as::<_, SubmissionRow>(&rows_sql), &binds);
+	let submissions = apply_binds!(submissions, &order_binds)
+		.bind(limit)
+		.bind(offset)
+		.fetch_all(&state.database)
+		.await
+		.map_err(error::from_db(Some(&user)))?;
 
 	let body = html! {
 		details open[any_search] {
 			summary { "Search" }
 			form method="get" {
-				label { "Submitter name (display name)" input type="text" name="submitter" value=[search_submitter.as_deref()]; }
-				label { "Problem name" input type="text" name="problem" value=[search_problem.as_deref()]; }
-				label { "Problem ID" input type="number" name="problem_id" value=[search_problem_id]; }
+				label { "Submitter name (display name)" input type="text" name="submitter" value=[search.submitter.as_deref()]; }
+				label { "Problem name" input type="text" name="problem" value=[search.problem.as_deref()]; }
+				label { "Problem ID" input type="number" name="problem_id" value=[search.problem_id]; }
+				label {
+					"Language"
+					select name="language" {
+						option value="" { "(any)" }
+						@for language in Language::ALL { option value=(language.repr()) selected[search.language == Some(*language)] { (language.name()) } }
+					}
+				}
+				label {
+					"Result"
+					select name="result" {
+						option value="" { "(any)" }
+						option value="correct" selected[matches!(search.result, Some(VerdictFilter::Correct))] { "Correct" }
+						option value="wrong" selected[matches!(search.result, Some(VerdictFilter::Wrong))] { "Wrong" }
+						option value="not_judged" selected[matches!(search.result, Some(VerdictFilter::NotJudged))] { "Not yet judged" }
+					}
+				}
+				label { "Submitted after (unix timestamp)" input type="number" name="after" value=[search.after.map(i64::from)]; }
+				label { "Submitted before (unix timestamp)" input type="number" name="before" value=[search.before.map(i64::from)]; }
+				label { input type="checkbox" name="reverse" value="true" checked[search.reverse == Some(true)]; " Oldest first" }
 				div.row {
 					input type="submit" value="Search";
 					a href="/admin/submissions" { "Stop searching" }
@@ -86,33 +244,94 @@ async fn submissions(
 			} } }
 		}
 		@if submissions.is_empty() { p { "Nothing here..." } }
-		(pagination.make_pager(num_submissions))
+		(pagination.make_pager(num_submissions, search.to_query()))
 	};
 
 	Ok(page("Submissions", Some(&user), &body).into_response())
 }
 
+search_query! { struct UsersSearch {
+	q: String,
+} }
+
+#[derive(sqlx::FromRow)]
+struct UserRow {
+	id: UserId,
+	username: String,
+	display_name: String,
+	creation_time: Timestamp,
+	permission_level: PermissionLevel,
+}
+
 async fn users(
 	extract::State(state): extract::State<Arc<State>>,
 	Admin(user): Admin,
 	pagination: RawPagination,
+	extract::Query(search): extract::Query<UsersSearch>,
 ) -> Result<Response, Response> {
+	let any_search = search.any_set();
+
 	let pagination = pagination.with_default_page_size(DEFAULT_PAGE_SIZE);
 	let limit = pagination.limit();
 	let offset = pagination.offset();
 
-	let num_users = query_scalar!(r#"select count(*) as "count: i64" from users"#)
+	let mut binds: Vec<SearchBind> = Vec::new();
+	let mut order_exprs: Vec<String> = Vec::new();
+	let mut order_binds: Vec<SearchBind> = Vec::new();
+	let where_clause = match search.q.as_deref().and_then(fts_query) {
+		Some(fts) => {
+			binds.push(SearchBind::Str(fts.clone()));
+			order_exprs.push(
+				"(select rank from users_fts where rowid = id and users_fts match ?)".to_owned(),
+			);
+			order_binds.push(SearchBind::Str(fts));
+			"id in (select rowid from users_fts where users_fts match ?)".to_owned()
+		}
+		// Either there's no search term, or it has no whitespace-separated terms (e.g. it's all
+		// whitespace); fall back to a plain substring scan in the latter case.
+		None => match search.q.as_deref() {
+			Some(q) => {
+				binds.push(SearchBind::Str(q.to_owned()));
+				binds.push(SearchBind::Str(q.to_owned()));
+				"(instr(username, ?) > 0 or instr(display_name, ?) > 0)".to_owned()
+			}
+			None => "1".to_owned(),
+		},
+	};
+
+	let count_sql = format!("select count(*) from users where {where_clause}");
+	let num_users = apply_binds!(query_scalar::<_, i64>(&count_sql), &binds)
 		.fetch_one(&state.database)
 		.await
 		.map_err(error::internal(Some(&user)))?;
 
-	let users =
-		query!(r#"select id, username, display_name, creation_time as "creation_time: Timestamp", permission_level as "permission_level: PermissionLevel" from users order by id desc limit ? offset ?"#, limit, offset)
-			.fetch_all(&state.database)
-			.await
-			.map_err(error::internal(Some(&user)))?;
+	let order_by = if order_exprs.is_empty() {
+		"id desc".to_owned()
+	} else {
+		format!("({}) asc, id desc", order_exprs.join(" + "))
+	};
+	let rows_sql = format!(
+		"select id, username, display_name, creation_time, permission_level from users where {where_clause} order by {order_by} limit ? offset ?"
+	);
+	let query = apply_binds!(query_as::<_, UserRow>(&rows_sql), &binds);
+	let users = apply_binds!(query, &order_binds)
+		.bind(limit)
+		.bind(offset)
+		.fetch_all(&state.database)
+		.await
+		.map_err(error::internal(Some(&user)))?;
 
 	let body = html! {
+		details open[any_search] {
+			summary { "Search" }
+			form method="get" {
+				label { "Username or display name" input type="text" name="q" value=[search.q.as_deref()]; }
+				div.row {
+					input type="submit" value="Search";
+					a href="/admin/users" { "Stop searching" }
+				}
+			}
+		}
 		table {
 			thead { tr {
 				th { "ID" }
@@ -130,119 +349,539 @@ async fn users(
 			} } }
 		}
 		@if users.is_empty() { p { "Nothing here..." } }
-		(pagination.make_pager(num_users))
+		(pagination.make_pager(num_users, search.to_query()))
 	};
 
 	Ok(page("Users", Some(&user), &body).into_response())
 }
 
-#[derive(Deserialize)]
-struct RunSqlForm {
-	sql: String,
+/// One decoded cell from a [`QueryBlock::Rows`] result, shared between the HTML table renderer and
+/// the CSV/JSON exporters so they only dispatch on the underlying SQLite storage type once.
+enum CellValue {
+	Null,
+	Str(String),
+	I64(i64),
+	F64(f64),
+	Bytes(Vec<u8>),
+	Undecodable,
 }
 
-async fn run_sql(
-	extract::State(state): extract::State<Arc<State>>,
-	Admin(user): Admin,
-	IfPost(post): IfPost<extract::Form<RunSqlForm>>,
-) -> Result<Response, Response> {
+fn decode_cell(ty: &sqlx::sqlite::SqliteTypeInfo, raw: sqlx::sqlite::SqliteValueRef<'_>) -> CellValue {
 	fn decode<'a, T: sqlx::Decode<'a, sqlx::Sqlite> + Type<sqlx::Sqlite>>(
 		raw: sqlx::sqlite::SqliteValueRef<'a>,
 	) -> T {
 		T::decode(raw).unwrap()
 	}
 
-	let results = if let Some(extract::Form(post)) = post {
-		let mut buf = String::new();
-		let mut results = query(&post.sql).fetch_many(&state.database).peekable();
-		let mut results = pin!(results);
-		while results.as_mut().peek().await.is_some() {
-			let mut first_row = true;
+	if raw.is_null() {
+		CellValue::Null
+	} else if *ty == <&str>::type_info() {
+		CellValue::Str(decode::<&str>(raw).to_owned())
+	} else if i64::compatible(ty) {
+		CellValue::I64(decode::<i64>(raw))
+	} else if f64::compatible(ty) {
+		CellValue::F64(decode::<f64>(raw))
+	} else if <&[u8]>::compatible(ty) {
+		CellValue::Bytes(decode::<&[u8]>(raw).to_owned())
+	} else {
+		CellValue::Undecodable
+	}
+}
 
-			buf += r#"<section class="query-block">"#;
+/// One `;`-separated statement's result from [`collect_blocks`]. A single submitted query can
+/// contain several statements, each producing either rows, an affected-row count, or its own
+/// error (which doesn't stop the remaining statements from running).
+enum QueryBlock {
+	Rows {
+		columns: Vec<(String, String)>,
+		rows: Vec<Vec<CellValue>>,
+	},
+	Affected {
+		changes: i64,
+		last_insert_id: i64,
+	},
+	Error(String),
+}
 
-			while let Some(row) = results.as_mut().peek().await.and_then(|res| {
-				if let Ok(Either::Right(row)) = res {
-					Some(row)
-				} else {
-					None
-				}
-			}) {
-				if first_row {
-					buf += "<table>";
-					html_into! { buf,
-						thead { tr {
-							@for column in row.columns() {
-								th { (render_debug(column.name())) ": " (column.type_info().name()) }
-							}
-						} }
-					};
-					buf += "<tbody>";
-				}
+/// Runs `sql` against `executor`, appending one [`QueryBlock`] per `;`-separated statement to
+/// `blocks`. Split out from `run_sql` so it can be raced against a [`tokio::time::timeout`] in
+/// safe mode: `blocks` lives outside the timed future, so whatever was already appended survives
+/// the future being dropped out from under it when the limit is hit.
+async fn collect_blocks<'e, E>(executor: E, sql: &str, blocks: &mut Vec<QueryBlock>)
+where
+	E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+{
+	let mut results = query(sql).fetch_many(executor).peekable();
+	let mut results = pin!(results);
+	while results.as_mut().peek().await.is_some() {
+		let mut columns: Vec<(String, String)> = Vec::new();
+		let mut rows: Vec<Vec<CellValue>> = Vec::new();
+
+		while let Some(row) = results.as_mut().peek().await.and_then(|res| {
+			if let Ok(Either::Right(row)) = res {
+				Some(row)
+			} else {
+				None
+			}
+		}) {
+			if columns.is_empty() {
+				columns = row
+					.columns()
+					.iter()
+					.map(|column| (column.name().to_owned(), column.type_info().name().to_owned()))
+					.collect();
+			}
+			rows.push(
+				row
+					.columns()
+					.iter()
+					.map(|column| decode_cell(column.type_info(), row.try_get_raw(column.ordinal()).unwrap()))
+					.collect(),
+			);
+
+			_ = results.next().await;
+		}
+
+		if !rows.is_empty() {
+			blocks.push(QueryBlock::Rows { columns, rows });
+		}
+
+		match results.next().await.unwrap() {
+			Ok(Either::Left(query_result)) => blocks.push(QueryBlock::Affected {
+				changes: query_result.rows_affected().try_into().unwrap(),
+				last_insert_id: query_result.last_insert_rowid(),
+			}),
+			// All rows were already processed above.
+			Ok(Either::Right(_row)) => unreachable!(),
+			Err(error) => blocks.push(QueryBlock::Error(error.to_string())),
+		}
+	}
+}
+
+fn render_cell(cell: &CellValue) -> Markup {
+	html! {
+		@match cell {
+			CellValue::Null => "NULL",
+			CellValue::Str(value) => (render_debug(value)),
+			CellValue::I64(value) => (value),
+			CellValue::F64(value) => (value),
+			CellValue::Bytes(bytes) => (hex::encode(bytes)),
+			CellValue::Undecodable => "(could not decode)",
+		}
+	}
+}
 
+fn render_blocks_html(blocks: &[QueryBlock]) -> String {
+	let mut buf = String::new();
+	for block in blocks {
+		buf += r#"<section class="query-block">"#;
+		match block {
+			QueryBlock::Rows { columns, rows } => {
 				html_into! { buf,
-					tr {
-						@for column in row.columns() { td {
-							@let ty = column.type_info();
-							@let raw_value = row.try_get_raw(column.ordinal()).unwrap();
-							@if raw_value.is_null() {
-								"NULL"
-							} @else if *ty == <&str>::type_info() {
-								(render_debug(decode::<&str>(raw_value)))
-							} @else if i64::compatible(ty) {
-								(decode::<i64>(raw_value))
-							} @else if f64::compatible(ty) {
-								(decode::<f64>(raw_value))
-							} @else if <&[u8]>::compatible(ty) {
-								(hex::encode(decode::<&[u8]>(raw_value)))
-							} @else {
-								"(could not decode)"
+					table {
+						thead { tr {
+							@for (name, ty) in columns {
+								th { (render_debug(name)) ": " (ty) }
 							}
 						} }
+						tbody { @for row in rows { tr {
+							@for cell in row { td { (render_cell(cell)) } }
+						} } }
 					}
 				}
-
-				first_row = false;
-				_ = results.next().await;
 			}
+			QueryBlock::Affected { changes, last_insert_id } => html_into! { buf,
+				p { "Query result: " (changes) " row" (s(*changes)) " changed, last insert ID " (last_insert_id) "." }
+			},
+			QueryBlock::Error(error) => html_into! { buf, p { "Error: " (error) } },
+		}
+		buf += "</section>";
+	}
+	buf
+}
 
-			// At least one row was printed.
-			if !first_row {
-				buf += "</tbody></table>";
-			}
+/// The columns and rows of the first [`QueryBlock::Rows`] in `blocks`, if any. The CSV/JSON
+/// exporters only make sense for a single tabular result, so later statements' rows (and any
+/// `Affected`/`Error` blocks) are ignored when exporting.
+fn first_rows(blocks: &[QueryBlock]) -> Option<(&[(String, String)], &[Vec<CellValue>])> {
+	blocks.iter().find_map(|block| match block {
+		QueryBlock::Rows { columns, rows } => Some((columns.as_slice(), rows.as_slice())),
+		_ => None,
+	})
+}
 
-			match results.next().await.unwrap() {
-				Ok(Either::Left(query_result)) => html_into! { buf,
-					@let changes = query_result.rows_affected().try_into().unwrap();
-					@let id = query_result.last_insert_rowid();
-					p { "Query result: " (changes) " row" (s(changes)) " changed, last insert ID " (id) "." }
-				},
-				// All rows were already processed above.
-				Ok(Either::Right(_row)) => unreachable!(),
-				Err(error) => html_into! { buf, p { "Error: " (error) } },
-			}
+fn cell_to_csv(cell: &CellValue) -> String {
+	match cell {
+		CellValue::Null | CellValue::Undecodable => String::new(),
+		CellValue::Str(value) => value.clone(),
+		CellValue::I64(value) => value.to_string(),
+		CellValue::F64(value) => value.to_string(),
+		CellValue::Bytes(bytes) => hex::encode(bytes),
+	}
+}
 
-			buf += "</section>";
+fn csv_field(field: &str) -> String {
+	if field.contains(['"', ',', '\n', '\r']) {
+		format!("\"{}\"", field.replace('"', "\"\""))
+	} else {
+		field.to_owned()
+	}
+}
+
+fn render_csv(columns: &[(String, String)], rows: &[Vec<CellValue>]) -> String {
+	let mut buf = String::new();
+	buf += &columns.iter().map(|(name, _)| csv_field(name)).collect::<Vec<_>>().join(",");
+	buf += "\r\n";
+	for row in rows {
+		buf += &row.iter().map(|cell| csv_field(&cell_to_csv(cell))).collect::<Vec<_>>().join(",");
+		buf += "\r\n";
+	}
+	buf
+}
+
+fn cell_to_json(cell: &CellValue) -> serde_json::Value {
+	match cell {
+		CellValue::Null | CellValue::Undecodable => serde_json::Value::Null,
+		CellValue::Str(value) => serde_json::Value::String(value.clone()),
+		CellValue::I64(value) => serde_json::Value::Number((*value).into()),
+		CellValue::F64(value) => serde_json::Number::from_f64(*value).map_or(serde_json::Value::Null, serde_json::Value::Number),
+		CellValue::Bytes(bytes) => serde_json::Value::String(hex::encode(bytes)),
+	}
+}
+
+fn render_json(columns: &[(String, String)], rows: &[Vec<CellValue>]) -> String {
+	let rows: Vec<serde_json::Value> = rows
+		.iter()
+		.map(|row| {
+			serde_json::Value::Object(
+				columns
+					.iter()
+					.zip(row)
+					.map(|((name, _), cell)| (name.clone(), cell_to_json(cell)))
+					.collect(),
+			)
+		})
+		.collect();
+	serde_json::to_string_pretty(&rows).expect("serializing query results as JSON")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum OutputFormat {
+	#[default]
+	Html,
+	Csv,
+	Json,
+}
+
+#[derive(Deserialize)]
+struct RunSqlForm {
+	sql: String,
+	/// Runs the query inside a transaction that is always rolled back, under a wall-clock limit
+	/// (see `CONFIG.sql_console_timeout_secs`), instead of executing it directly against the pool.
+	#[serde(default)]
+	safe: bool,
+	#[serde(default)]
+	format: OutputFormat,
+}
+
+fn run_sql_form(user: &User) -> Markup {
+	html! {
+		form method="post" {
+			(csrf_field(user))
+			textarea name="sql" cols="40" required {}
+			label { input type="checkbox" name="safe" value="true"; " Safe mode (run in a transaction that is always rolled back, under a wall-clock limit)" }
+			label {
+				"Export as "
+				select name="format" {
+					option value="html" { "HTML table" }
+					option value="csv" { "CSV" }
+					option value="json" { "JSON" }
+				}
+			}
+			input type="submit" value="Run";
 		}
+	}
+}
 
-		Some(buf)
-	} else {
-		None
+/// Statement-leading keywords that would let submitted SQL escape safe mode's wrapping
+/// transaction: e.g. a bare `commit;` persists everything run before it for real, and then
+/// `run_sql`'s own `transaction.rollback()` has nothing left to roll back. Checked per
+/// `;`-separated statement, case-insensitively, before anything in the submission runs.
+const TRANSACTION_CONTROL_KEYWORDS: &[&str] =
+	&["begin", "commit", "end", "rollback", "savepoint", "release"];
+
+fn contains_transaction_control(sql: &str) -> bool {
+	sql.split(';').any(|statement| {
+		let first_word = statement
+			.trim_start()
+			.split(|c: char| c.is_whitespace() || c == '(')
+			.next()
+			.unwrap_or("");
+		TRANSACTION_CONTROL_KEYWORDS
+			.iter()
+			.any(|keyword| first_word.eq_ignore_ascii_case(keyword))
+	})
+}
+
+async fn run_sql(
+	extract::State(state): extract::State<Arc<State>>,
+	Admin(user): Admin,
+	IfPost(post): IfPost<Csrf<extract::Form<RunSqlForm>>>,
+) -> Result<Response, Response> {
+	let Some(Csrf(extract::Form(post))) = post else {
+		let body = html! {
+			p { "Please take care to limit the size of your query results." }
+			(run_sql_form(&user))
+		};
+		return Ok(page("Run SQL", Some(&user), &body).into_response());
 	};
 
+	if post.safe && contains_transaction_control(&post.sql) {
+		return Err(ErrorResponse::bad_request(
+			"Safe mode queries may not contain transaction-control statements (BEGIN, COMMIT, END, ROLLBACK, SAVEPOINT, RELEASE), since those would let the query commit for real.",
+		)
+		.into_response(Some(&user)));
+	}
+
+	let mut blocks = Vec::new();
+	if post.safe {
+		let mut transaction = state.database.begin().await.map_err(error::from_db(Some(&user)))?;
+		let timeout_secs = crate::CONFIG.sql_console_timeout_secs;
+		let timed_out = tokio::time::timeout(
+			std::time::Duration::from_secs(timeout_secs),
+			collect_blocks(&mut transaction, &post.sql, &mut blocks),
+		)
+		.await
+		.is_err();
+		// Safe mode never persists changes, win or lose.
+		transaction
+			.rollback()
+			.await
+			.map_err(error::from_db(Some(&user)))?;
+		if timed_out {
+			blocks.push(QueryBlock::Error(format!(
+				"Query aborted after exceeding the {timeout_secs}-second wall-clock limit; showing partial results."
+			)));
+		}
+	} else {
+		collect_blocks(&state.database, &post.sql, &mut blocks).await;
+	}
+
+	match post.format {
+		OutputFormat::Html => {
+			let body = html! {
+				(maud::PreEscaped(render_blocks_html(&blocks)))
+				hr;
+				p { "Please take care to limit the size of your query results." }
+				(run_sql_form(&user))
+			};
+			Ok(page("Run SQL", Some(&user), &body).into_response())
+		}
+		OutputFormat::Csv | OutputFormat::Json => {
+			let Some((columns, rows)) = first_rows(&blocks) else {
+				return Err(
+					ErrorResponse::bad_request("The query returned no rows to export.")
+						.into_response(Some(&user)),
+				);
+			};
+			let (content_type, extension, content) = match post.format {
+				OutputFormat::Csv => ("text/csv; charset=utf-8", "csv", render_csv(columns, rows)),
+				OutputFormat::Json => ("application/json", "json", render_json(columns, rows)),
+				OutputFormat::Html => unreachable!(),
+			};
+			let content_disposition = format!("attachment; filename=\"query-results.{extension}\"");
+			Ok((
+				[
+					("Content-Type", content_type),
+					("Content-Disposition", content_disposition.as_str()),
+				],
+				content,
+			)
+				.into_response())
+		}
+	}
+}
+
+async fn diagnostics(
+	extract::State(state): extract::State<Arc<State>>,
+	Admin(user): Admin,
+) -> Result<Response, Response> {
+	let db_healthy = query_scalar!(r#"select 1 as "one: i64""#)
+		.fetch_one(&state.database)
+		.await
+		.is_ok();
+
+	let now_value = now();
+	let active_sessions = query_scalar!(
+		r#"select count(*) as "count: i64" from sessions where expiration >= ?"#,
+		now_value,
+	)
+	.fetch_one(&state.database)
+	.await
+	.map_err(error::internal(Some(&user)))?;
+
+	let users_by_level = query!(
+		r#"select permission_level as "permission_level: PermissionLevel", count(*) as "count: i64" from users group by permission_level order by permission_level"#,
+	)
+	.fetch_all(&state.database)
+	.await
+	.map_err(error::internal(Some(&user)))?;
+
+	let versions = Language::ALL.iter().zip(state.sandbox.versions());
+
 	let body = html! {
-		@if let Some(results) = results {
-			(maud::PreEscaped(results))
-			hr;
+		h2 { "Health" }
+		p { "Database: " (if db_healthy { "OK" } else { "ERROR" }) }
+		p { "Sandbox image: " @if state.sandbox.image_id().is_empty() { "missing" } @else { code { (state.sandbox.image_id()) } } }
+		h2 { "Language versions" }
+		ul { @for (language, version) in versions {
+			li { (language.name()) ": " (version) }
+		} }
+		h2 { "Sessions" }
+		p { (active_sessions) " active session" (s(active_sessions)) "." }
+		h2 { "Users by permission level" }
+		ul { @for row in &users_by_level {
+			li { (row.permission_level.name()) ": " (row.count) }
+		} }
+		h2 { "Backup" }
+		p { a href="/admin/backup" { "Download database backup" } }
+		h2 { "Restore" }
+		p.preserve-space { "Restoring replaces the entire database with the uploaded file the next time the server starts. This cannot be undone; consider downloading a fresh backup first." }
+		form method="post" action={"/admin/restore?"(csrf_query(&user))} enctype="multipart/form-data" {
+			label { "Backup file" input type="file" name="backup" required; }
+			label { input type="checkbox" name="confirm" value="true"; " I understand this will replace the current database." }
+			input type="submit" value="Restore";
 		}
-		p { "Please take care to limit the size of your query results." }
-		form method="post" {
-			textarea name="sql" cols="40" required {}
-			input type="submit" value="Run";
+	};
+
+	Ok(page("Diagnostics", Some(&user), &body).into_response())
+}
+
+async fn backup(
+	extract::State(state): extract::State<Arc<State>>,
+	Admin(user): Admin,
+) -> Result<Response, Response> {
+	let temp_dir = temp_dir::TempDir::new().map_err(error::internal(Some(&user)))?;
+	let backup_path = temp_dir.path().join("backup.sqlite");
+	let backup_path_str = backup_path.to_str().expect("temp dir path is valid UTF-8");
+
+	query!("vacuum into ?", backup_path_str)
+		.execute(&state.database)
+		.await
+		.map_err(error::from_db(Some(&user)))?;
+
+	let backup_bytes = tokio::fs::read(&backup_path)
+		.await
+		.map_err(error::internal(Some(&user)))?;
+
+	mod_log::record(
+		&state,
+		user.id,
+		mod_log::Action::DownloadBackup,
+		mod_log::TargetType::System,
+		0,
+		None,
+	)
+	.await
+	.map_err(error::from_db(Some(&user)))?;
+
+	let content_disposition = format!("attachment; filename=\"backup-{}.sqlite\"", i64::from(now()));
+	let response = (
+		[
+			("Content-Disposition", content_disposition.as_str()),
+			("Content-Type", "application/vnd.sqlite3"),
+		],
+		backup_bytes,
+	);
+	Ok(response.into_response())
+}
+
+async fn restore(
+	extract::State(state): extract::State<Arc<State>>,
+	Admin(user): Admin,
+	Csrf(mut upload): Csrf<extract::Multipart>,
+) -> Result<Response, Response> {
+	let mut backup_bytes = None;
+	let mut confirmed = false;
+
+	while let Some(field) = upload
+		.next_field()
+		.await
+		.map_err(error::internal(Some(&user)))?
+	{
+		match field.name() {
+			Some("backup") => {
+				backup_bytes =
+					Some(field.bytes().await.map_err(error::internal(Some(&user)))?);
+			}
+			Some("confirm") => confirmed = true,
+			_ => {}
 		}
+	}
+
+	if !confirmed {
+		return Err(ErrorResponse::bad_request(
+			"You must check the confirmation box to restore the database.",
+		)
+		.into_response(Some(&user)));
+	}
+	let Some(backup_bytes) = backup_bytes else {
+		return Err(
+			ErrorResponse::bad_request("No backup file was uploaded.").into_response(Some(&user)),
+		);
+	};
+
+	if !backup_bytes.starts_with(b"SQLite format 3\0") {
+		return Err(ErrorResponse::bad_request("The uploaded file is not a SQLite database.")
+			.into_response(Some(&user)));
+	}
+
+	tokio::fs::write(crate::RESTORE_PENDING_PATH, &backup_bytes)
+		.await
+		.map_err(error::internal(Some(&user)))?;
+
+	// Make sure it's actually a readable SQLite database, not just a file with the right magic bytes,
+	// before committing to restoring it on the next startup.
+	let check_result = async {
+		let pool = sqlx::SqlitePool::connect(&format!("sqlite:{}", crate::RESTORE_PENDING_PATH))
+			.await?;
+		let result = query_scalar::<_, i64>("select count(*) from sqlite_master")
+			.fetch_one(&pool)
+			.await;
+		pool.close().await;
+		result
+	}
+	.await;
+
+	if let Err(error) = check_result {
+		_ = tokio::fs::remove_file(crate::RESTORE_PENDING_PATH).await;
+		return Err(ErrorResponse::bad_request(format!(
+			"The uploaded database looks corrupt: {error}"
+		))
+		.into_response(Some(&user)));
+	}
+
+	mod_log::record(
+		&state,
+		user.id,
+		mod_log::Action::RestoreBackup,
+		mod_log::TargetType::System,
+		0,
+		None,
+	)
+	.await
+	.map_err(error::from_db(Some(&user)))?;
+
+	let body = html! {
+		h1 { "Restore Staged" }
+		p { "The uploaded database has been staged. Restart the server to complete the restore." }
+		p { a href="/admin" { "Back" } }
 	};
 
-	Ok(page("Run SQL", Some(&user), &body).into_response())
+	Ok(page("Restore Staged", Some(&user), &body)
+		.custom_title()
+		.into_response())
 }
 
 async fn admin(Admin(user): Admin) -> Response {
@@ -250,16 +889,23 @@ async fn admin(Admin(user): Admin) -> Response {
 		p { a href="/admin/submissions" { "View all submissions" } }
 		p { a href="/admin/users" { "View all users" } }
 		p { a href="/admin/sql" { "Run SQL" } }
+		p { a href="/admin/diagnostics" { "Diagnostics and backup" } }
 	};
 
 	page("Admin", Some(&user), &body).into_response()
 }
 
-pub fn router() -> axum::Router<Arc<State>> {
+pub fn router(state: Arc<State>) -> axum::Router<Arc<State>> {
+	let sql = axum::Router::new()
+		.route("/sql", get(run_sql).post(run_sql))
+		.route_layer(ratelimit::layer(state, ratelimit::RouteClass::Sql));
 	let router = axum::Router::new()
 		.route("/", get(admin))
 		.route("/submissions", get(submissions))
 		.route("/users", get(users))
-		.route("/sql", get(run_sql).post(run_sql));
+		.merge(sql)
+		.route("/diagnostics", get(diagnostics))
+		.route("/backup", get(backup))
+		.route("/restore", axum::routing::post(restore));
 	axum::Router::new().nest("/admin", router)
 }
@@ -0,0 +1,195 @@
+use std::sync::Arc;
+
+use axum::extract;
+use axum::response::Response;
+use axum::routing::{get, post};
+use axum::Json;
+use sqlx::query;
+
+use crate::error::ErrorResponse;
+use crate::extract::auth::{ApiUser, User};
+use crate::extract::pagination::RawPagination;
+use crate::model::{JudgeStatus, Language, PermissionLevel, ProblemId, SubmissionId, UserId};
+use crate::routes::problems::new::{self, Problem};
+use crate::routes::problems::problem::{self, ProblemPermissionLevel};
+use crate::routes::submissions::{self, SubmissionsSearch};
+use crate::sandbox::TestResponse;
+use crate::time::Timestamp;
+use crate::State;
+
+async fn not_found_json() -> Response {
+	ErrorResponse::not_found().await.into_json_response()
+}
+
+#[derive(serde::Serialize)]
+struct CreateProblemResponse {
+	id: ProblemId,
+}
+
+async fn create_problem(
+	extract::State(state): extract::State<Arc<State>>,
+	ApiUser(user): ApiUser,
+	Json(post): Json<Problem>,
+) -> Result<Json<CreateProblemResponse>, Response> {
+	if user.permission_level < PermissionLevel::ProblemAuthor {
+		return Err(
+			ErrorResponse::bad_request("You do not have permission to create problems.")
+				.into_json_response(),
+		);
+	}
+
+	let id = new::handle_post(&state, &user, &post)
+		.await
+		.map_err(ErrorResponse::into_json_response)?;
+
+	Ok(Json(CreateProblemResponse { id }))
+}
+
+#[derive(serde::Serialize)]
+struct ProblemMetadata {
+	id: ProblemId,
+	name: String,
+	description: String,
+	time_limit: u32,
+	visible: bool,
+	creation_time: Timestamp,
+	created_by: Option<UserId>,
+	num_submissions: i64,
+	num_correct_submissions: i64,
+}
+
+async fn problem_metadata(
+	extract::State(state): extract::State<Arc<State>>,
+	user: Option<User>,
+	extract::Path(problem_id): extract::Path<ProblemId>,
+) -> Result<Json<ProblemMetadata>, Response> {
+	let Some(row) = query!(
+		r#"select name, description, time_limit as "time_limit: u32", visible as "visible: bool", created_by, creation_time as "creation_time: Timestamp", (select count(*) from submissions where for_problem = problems.id) as "num_submissions!: i64", (select count(*) from submissions where for_problem = problems.id and result like 'o%') as "num_correct_submissions!: i64" from problems where id = ?"#,
+		problem_id,
+	)
+	.fetch_optional(&state.database)
+	.await
+	.map_err(|error| ErrorResponse::from_db(error).into_json_response())?
+	else {
+		return Err(not_found_json().await);
+	};
+
+	if problem::permission_level(user.as_ref(), row.created_by, row.visible)
+		< ProblemPermissionLevel::View
+	{
+		return Err(not_found_json().await);
+	}
+
+	Ok(Json(ProblemMetadata {
+		id: problem_id,
+		name: row.name,
+		description: row.description,
+		time_limit: row.time_limit,
+		visible: row.visible,
+		creation_time: row.creation_time,
+		created_by: row.created_by,
+		num_submissions: row.num_submissions,
+		num_correct_submissions: row.num_correct_submissions,
+	}))
+}
+
+#[derive(serde::Serialize)]
+struct SubmitResponse {
+	id: SubmissionId,
+}
+
+async fn submit(
+	extract::State(state): extract::State<Arc<State>>,
+	ApiUser(user): ApiUser,
+	extract::Path(problem_id): extract::Path<ProblemId>,
+	Json(post): Json<problem::Post>,
+) -> Result<Json<SubmitResponse>, Response> {
+	let id = problem::submit(&state, &user, problem_id, &post)
+		.await
+		.map_err(ErrorResponse::into_json_response)?;
+
+	Ok(Json(SubmitResponse { id }))
+}
+
+#[derive(serde::Serialize)]
+struct SubmissionStatus {
+	id: SubmissionId,
+	problem_id: ProblemId,
+	submitter_id: UserId,
+	language: Language,
+	submission_time: Timestamp,
+	judged_time: Option<Timestamp>,
+	judge_status: Option<JudgeStatus>,
+	result: Option<TestResponse>,
+}
+
+async fn submission_status(
+	extract::State(state): extract::State<Arc<State>>,
+	user: Option<User>,
+	extract::Path(submission_id): extract::Path<SubmissionId>,
+) -> Result<Json<SubmissionStatus>, Response> {
+	let Some(row) = query!(
+		r#"select for_problem as problem_id, problem.created_by as problem_author, submitter, language as "language: Language", submission_time as "submission_time: Timestamp", judged_time as "judged_time: Timestamp", result as "result: TestResponse", judge_status as "judge_status: JudgeStatus" from submissions inner join problems as problem on submissions.for_problem = problem.id where submissions.id = ?"#,
+		submission_id,
+	)
+	.fetch_optional(&state.database)
+	.await
+	.map_err(|error| ErrorResponse::from_db(error).into_json_response())?
+	else {
+		return Err(not_found_json().await);
+	};
+
+	let permission_level =
+		submissions::permission_level(user.as_ref(), row.submitter, row.problem_author);
+	if !permission_level.can_view() {
+		return Err(not_found_json().await);
+	}
+
+	Ok(Json(SubmissionStatus {
+		id: submission_id,
+		problem_id: row.problem_id,
+		submitter_id: row.submitter,
+		language: row.language,
+		submission_time: row.submission_time,
+		judged_time: row.judged_time,
+		judge_status: row.judge_status,
+		result: row.result,
+	}))
+}
+
+#[derive(serde::Serialize)]
+struct SubmissionsListResponse {
+	total: i64,
+	submissions: Vec<submissions::SubmissionListEntry>,
+}
+
+async fn submissions_list(
+	extract::State(state): extract::State<Arc<State>>,
+	ApiUser(user): ApiUser,
+	pagination: RawPagination,
+	extract::Query(search): extract::Query<SubmissionsSearch>,
+) -> Result<Json<SubmissionsListResponse>, Response> {
+	let pagination = pagination.with_default_page_size(submissions::DEFAULT_PAGE_SIZE);
+
+	let (total, submissions) = submissions::search(
+		&state,
+		&user,
+		&search,
+		pagination.limit(),
+		pagination.offset(),
+	)
+	.await
+	.map_err(|error| ErrorResponse::from_db(error).into_json_response())?;
+
+	Ok(Json(SubmissionsListResponse { total, submissions }))
+}
+
+pub fn router() -> axum::Router<Arc<State>> {
+	let v1 = axum::Router::new()
+		.route("/problems", post(create_problem))
+		.route("/problem/:id", get(problem_metadata))
+		.route("/problem/:id/submissions", post(submit))
+		.route("/submission/:id", get(submission_status))
+		.route("/submissions", get(submissions_list));
+	axum::Router::new().nest("/api/v1", v1)
+}
@@ -0,0 +1,45 @@
+use std::sync::Arc;
+
+use axum::extract;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use maud::html;
+use sqlx::query;
+
+use crate::error;
+use crate::extract::auth::User;
+use crate::routes::password_reset::ActionQuery;
+use crate::template::page;
+use crate::State;
+
+async fn verify(
+	extract::State(state): extract::State<Arc<State>>,
+	user: Option<User>,
+	extract::Query(query): extract::Query<ActionQuery>,
+) -> Result<Response, Response> {
+	let result = query!(
+		"update users set email_verified = true, email_verify_key = random() where id = ? and email_verify_key = ?",
+		query.user,
+		query.key,
+	)
+	.execute(&state.database)
+	.await
+	.map_err(error::internal(user.as_ref()))?;
+
+	if result.rows_affected() > 0 {
+		tracing::info!(user=?query.user, "email verified");
+	} else {
+		tracing::warn!(user=?query.user, req_user=?user, "invalid email verification request");
+	}
+
+	let body = html! {
+		p { "If these parameters were correct, the email address on the account has been verified." }
+	};
+
+	Ok(page("Verify Email", user.as_ref(), &body).into_response())
+}
+
+pub fn router() -> axum::Router<Arc<State>> {
+	let router = axum::Router::new().route("/verify", get(verify));
+	axum::Router::new().nest("/email", router)
+}
@@ -1,79 +1,318 @@
 use std::sync::Arc;
 
 use axum::extract;
-use axum::http::StatusCode;
+use axum::http::{HeaderMap, StatusCode};
 use axum::response::{IntoResponse, Redirect, Response};
 use axum::routing::get;
 use maud::html;
 use serde::Deserialize;
-use sqlx::query;
+use sqlx::{query, query_scalar};
 
 use crate::error::ErrorResponse;
 use crate::extract::auth::{self, Token, User};
 use crate::extract::if_post::IfPost;
 use crate::extract::return_to::ReturnTo;
+use crate::model::UserId;
 use crate::template::{page, BannerKind};
-use crate::State;
+use crate::time::{minutes, now, Duration, Timestamp};
+use crate::totp;
+use crate::{ratelimit, State, CONFIG};
+
+const PENDING_LOGIN_DURATION: Duration = minutes(5);
 
 #[derive(Deserialize)]
 struct Form {
-	username: String,
-	password: String,
+	username: Option<String>,
+	password: Option<String>,
+	pending: Option<String>,
+	code: Option<String>,
 }
 
-async fn handle_post(state: &State, form: Form) -> Result<Token, ErrorResponse> {
-	let entry = query!(
-		r#"select id as "id!", password as hash from users where username = ?"#,
-		form.username,
+enum Outcome {
+	LoggedIn(Token),
+	/// Password was correct (or a 2FA attempt just failed); `pending` identifies the in-progress login.
+	NeedsTwoFactor {
+		pending: String,
+		error: Option<&'static str>,
+	},
+}
+
+/// Keys to track brute-force attempts under: one for the account being targeted, one for the
+/// client's address, so a single IP hammering many usernames is throttled just like the reverse.
+fn attempt_keys(user_key: &str, headers: &HeaderMap) -> Vec<String> {
+	let mut keys = vec![format!("user:{user_key}")];
+	if let Some(ip) = auth::client_ip(headers) {
+		keys.push(format!("ip:{ip}"));
+	}
+	keys
+}
+
+fn check_attempts(state: &State, keys: &[String]) -> Result<(), ErrorResponse> {
+	for key in keys {
+		if let Err(retry_after) = state.login_limiter.check(key) {
+			return Err(ErrorResponse::too_many_requests(
+				"Too many failed login attempts. Please try again later.",
+				retry_after.as_secs(),
+			));
+		}
+	}
+	Ok(())
+}
+
+fn record_failed_attempt(state: &State, keys: &[String]) {
+	for key in keys {
+		state.login_limiter.record_failure(key);
+	}
+}
+
+fn record_successful_attempt(state: &State, keys: &[String]) {
+	for key in keys {
+		state.login_limiter.record_success(key);
+	}
+}
+
+async fn begin_two_factor(state: &State, user_id: UserId) -> Result<String, ErrorResponse> {
+	let pending_token: [u8; 16] = rand::random();
+	let expiration = now() + PENDING_LOGIN_DURATION;
+	query!(
+		"insert into pending_logins (token, user, expiration) values (?, ?, ?)",
+		&pending_token[..],
+		user_id,
+		expiration,
+	)
+	.execute(&state.database)
+	.await
+	.map_err(ErrorResponse::internal)?;
+	Ok(hex::encode(pending_token))
+}
+
+/// Checks `code` as a TOTP code, falling back to a recovery code. Returns `false` if neither matched.
+async fn verify_two_factor(
+	state: &State,
+	user_id: UserId,
+	code: &str,
+) -> Result<bool, ErrorResponse> {
+	let totp_row = query!(
+		r#"select secret as "secret: totp::Secret", last_accepted_step from totp_secrets where user = ?"#,
+		user_id,
 	)
 	.fetch_optional(&state.database)
 	.await
 	.map_err(ErrorResponse::internal)?;
 
-	if let Some(entry) = entry {
-		if bcrypt::verify(&form.password, &entry.hash).map_err(ErrorResponse::internal)? {
-			let token = auth::log_in(state, entry.id).await?;
-			return Ok(token);
+	if let Some(totp_row) = totp_row {
+		if let Some(step) = totp_row.secret.verify(code, now(), totp_row.last_accepted_step) {
+			query!(
+				"update totp_secrets set last_accepted_step = ? where user = ?",
+				step,
+				user_id,
+			)
+			.execute(&state.database)
+			.await
+			.map_err(ErrorResponse::internal)?;
+			return Ok(true);
 		}
 	}
 
-	Err(ErrorResponse::bad_request(
-		"Username or password is incorrect.",
-	))
+	let recovery_codes = query!(
+		r#"select id as "id!", hash as "hash: crate::password::Hash" from recovery_codes where user = ?"#,
+		user_id,
+	)
+	.fetch_all(&state.database)
+	.await
+	.map_err(ErrorResponse::internal)?;
+
+	for recovery_code in recovery_codes {
+		if recovery_code.hash.verify(code).unwrap_or(false) {
+			query!("delete from recovery_codes where id = ?", recovery_code.id)
+				.execute(&state.database)
+				.await
+				.map_err(ErrorResponse::internal)?;
+			return Ok(true);
+		}
+	}
+
+	Ok(false)
+}
+
+async fn continue_pending(
+	state: &State,
+	pending: &str,
+	code: &str,
+	headers: &HeaderMap,
+) -> Result<Outcome, ErrorResponse> {
+	let pending_token =
+		hex::decode(pending).map_err(|_| ErrorResponse::bad_request("Invalid login attempt."))?;
+
+	let Some(row) = query!(
+		r#"select user as "user: UserId", expiration as "expiration: Timestamp" from pending_logins where token = ?"#,
+		pending_token,
+	)
+	.fetch_optional(&state.database)
+	.await
+	.map_err(ErrorResponse::internal)?
+	else {
+		return Err(ErrorResponse::bad_request(
+			"Your login attempt has expired. Please log in again.",
+		));
+	};
+
+	if row.expiration.is_in_past() {
+		query!("delete from pending_logins where token = ?", pending_token)
+			.execute(&state.database)
+			.await
+			.map_err(ErrorResponse::internal)?;
+		return Err(ErrorResponse::bad_request(
+			"Your login attempt has expired. Please log in again.",
+		));
+	}
+
+	let keys = attempt_keys(&row.user.to_string(), headers);
+	check_attempts(state, &keys)?;
+
+	if !verify_two_factor(state, row.user, code).await? {
+		record_failed_attempt(state, &keys);
+		return Ok(Outcome::NeedsTwoFactor {
+			pending: pending.to_owned(),
+			error: Some("Invalid two-factor or recovery code."),
+		});
+	}
+	record_successful_attempt(state, &keys);
+
+	query!("delete from pending_logins where token = ?", pending_token)
+		.execute(&state.database)
+		.await
+		.map_err(ErrorResponse::internal)?;
+
+	let token = auth::log_in(state, row.user, headers).await?;
+	Ok(Outcome::LoggedIn(token))
+}
+
+async fn handle_post(
+	state: &State,
+	form: &Form,
+	headers: &HeaderMap,
+) -> Result<Outcome, ErrorResponse> {
+	if let Some(pending) = &form.pending {
+		let code = form.code.as_deref().unwrap_or_default();
+		return continue_pending(state, pending, code, headers).await;
+	}
+
+	let username = form.username.as_deref().unwrap_or_default();
+	let password = form.password.as_deref().unwrap_or_default();
+
+	let keys = attempt_keys(username, headers);
+	check_attempts(state, &keys)?;
+
+	let entry = query!(
+		r#"select id as "id!", password as hash, blocked as "blocked!: bool" from users where username = ?"#,
+		username,
+	)
+	.fetch_optional(&state.database)
+	.await
+	.map_err(ErrorResponse::internal)?;
+
+	let Some(entry) = entry else {
+		record_failed_attempt(state, &keys);
+		return Err(ErrorResponse::bad_request(
+			"Username or password is incorrect.",
+		));
+	};
+
+	if entry.blocked {
+		tracing::warn!(username, "blocked user attempted to log in");
+		return Err(ErrorResponse::bad_request(
+			"This account has been blocked. Contact the administrator.",
+		));
+	}
+
+	if !bcrypt::verify(password, &entry.hash).map_err(ErrorResponse::internal)? {
+		record_failed_attempt(state, &keys);
+		return Err(ErrorResponse::bad_request(
+			"Username or password is incorrect.",
+		));
+	}
+	record_successful_attempt(state, &keys);
+
+	let has_totp = query_scalar!(
+		r#"select count(*) as "count: i64" from totp_secrets where user = ?"#,
+		entry.id,
+	)
+	.fetch_one(&state.database)
+	.await
+	.map_err(ErrorResponse::internal)?
+		> 0;
+
+	if has_totp {
+		let pending = begin_two_factor(state, entry.id).await?;
+		return Ok(Outcome::NeedsTwoFactor {
+			pending,
+			error: None,
+		});
+	}
+
+	let token = auth::log_in(state, entry.id, headers).await?;
+	Ok(Outcome::LoggedIn(token))
 }
 
 async fn handler(
 	extract::State(state): extract::State<Arc<State>>,
 	user: Option<User>,
 	extract::Query(return_to): extract::Query<ReturnTo>,
+	headers: HeaderMap,
 	IfPost(post): IfPost<extract::Form<Form>>,
 ) -> Response {
-	let error = if let Some(extract::Form(post)) = post {
-		match handle_post(&state, post).await {
-			Ok(token) => return (&token, Redirect::to(return_to.path())).into_response(),
-			Err(error) => Some(error),
+	let mut error = None;
+	let mut two_factor = None;
+
+	if let Some(extract::Form(post)) = post {
+		match handle_post(&state, &post, &headers).await {
+			Ok(Outcome::LoggedIn(token)) => {
+				return (&token, Redirect::to(return_to.path())).into_response()
+			}
+			Ok(Outcome::NeedsTwoFactor {
+				pending,
+				error: code_error,
+			}) => {
+				two_factor = Some((pending, code_error));
+			}
+			Err(err) => error = Some(err),
 		}
-	} else {
-		None
-	};
+	}
 
 	let status = error.as_ref().map_or(StatusCode::OK, |error| error.status);
 	let body = html! {
 		h1 { "Log In" }
-		form method="post" {
-			label for="username" { "Username" input id="username" name="username" type="text" autocomplete="username" required; }
-			label for="password" { "Password" input id="password" name="password" type="password" autocomplete="current-password" required; }
-			input type="submit" value="Log in";
+		@if let Some((pending, _)) = &two_factor {
+			form method="post" {
+				input type="hidden" name="pending" value=(pending);
+				label for="code" { "Two-factor code (or a recovery code)" input id="code" name="code" type="text" autocomplete="one-time-code" required autofocus; }
+				input type="submit" value="Verify";
+			}
+		} @else {
+			form method="post" {
+				label for="username" { "Username" input id="username" name="username" type="text" autocomplete="username" required; }
+				label for="password" { "Password" input id="password" name="password" type="password" autocomplete="current-password" required; }
+				input type="submit" value="Log in";
+			}
+			p { a href="/password-reset" { "Forgot your password?" } }
+			@for provider in &CONFIG.oauth {
+				p { a href=(return_to.add_to_path(&format!("/oauth/{}/start", provider.name))) { "Sign in with " (provider.name) } }
+			}
 		}
 	};
 
 	let mut page = page("Log In", user.as_ref(), &body);
 	if let Some(error) = &error {
 		page = page.with_banner(BannerKind::Error, &error.message);
+	} else if let Some((_, Some(code_error))) = &two_factor {
+		page = page.with_banner(BannerKind::Error, code_error);
 	}
 	(status, page).into_response()
 }
 
-pub fn router() -> axum::Router<Arc<State>> {
-	axum::Router::new().route("/login", get(handler).post(handler))
+pub fn router(state: Arc<State>) -> axum::Router<Arc<State>> {
+	axum::Router::new()
+		.route("/login", get(handler).post(handler))
+		.route_layer(ratelimit::layer(state, ratelimit::RouteClass::Auth))
 }
@@ -4,22 +4,32 @@ use crate::State;
 
 mod about;
 mod admin;
+mod api;
+mod email;
 mod log_in;
 mod log_out;
+mod mod_log;
+mod oauth;
+mod password_reset;
 mod problems;
 mod register;
 mod root;
 mod submissions;
 mod users;
 
-pub fn router() -> axum::Router<Arc<State>> {
+pub fn router(state: Arc<State>) -> axum::Router<Arc<State>> {
 	axum::Router::new()
 		.merge(about::router())
-		.merge(admin::router())
-		.merge(log_in::router())
+		.merge(admin::router(Arc::clone(&state)))
+		.merge(api::router())
+		.merge(email::router())
+		.merge(log_in::router(Arc::clone(&state)))
 		.merge(log_out::router())
+		.merge(mod_log::router())
+		.merge(oauth::router())
+		.merge(password_reset::router())
 		.merge(problems::router())
-		.merge(register::router())
+		.merge(register::router(state))
 		.merge(root::router())
 		.merge(submissions::router())
 		.merge(users::router())
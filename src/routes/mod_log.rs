@@ -0,0 +1,101 @@
+use std::sync::Arc;
+
+use axum::extract;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use maud::html;
+use sqlx::{query, query_scalar};
+
+use crate::extract::auth::Admin;
+use crate::extract::pagination::RawPagination;
+use crate::mod_log::{Action, TargetType};
+use crate::model::{Id, UserId};
+use crate::template::page;
+use crate::util::search_query;
+use crate::{error, State};
+
+const DEFAULT_PAGE_SIZE: u32 = 30;
+
+search_query! { struct Search {
+	actor_id: UserId,
+	action: String,
+	target_type: String,
+	target_id: Id,
+} }
+
+async fn handler(
+	extract::State(state): extract::State<Arc<State>>,
+	Admin(user): Admin,
+	pagination: RawPagination,
+	extract::Query(search): extract::Query<Search>,
+) -> Result<Response, Response> {
+	let any_search = search.any_set();
+
+	let pagination = pagination.with_default_page_size(DEFAULT_PAGE_SIZE);
+	let limit = pagination.limit();
+	let offset = pagination.offset();
+
+	let num_entries = query_scalar!(
+		r#"select count(*) as "count: i64" from mod_log where (?1 is null or actor = ?1) and (?2 is null or action = ?2) and (?3 is null or target_type = ?3) and (?4 is null or target_id = ?4)"#,
+		search.actor_id,
+		search.action,
+		search.target_type,
+		search.target_id,
+	)
+	.fetch_one(&state.database)
+	.await
+	.map_err(error::from_db(Some(&user)))?;
+
+	let entries = query!(
+		r#"select mod_log.id as "id!", actor, users.display_name as actor_name, action as "action: Action", target_type as "target_type: TargetType", target_id, detail, time as "time: crate::time::Timestamp" from mod_log inner join users on mod_log.actor = users.id where (?3 is null or actor = ?3) and (?4 is null or action = ?4) and (?5 is null or target_type = ?5) and (?6 is null or target_id = ?6) order by mod_log.id desc limit ?1 offset ?2"#,
+		limit,
+		offset,
+		search.actor_id,
+		search.action,
+		search.target_type,
+		search.target_id,
+	)
+	.fetch_all(&state.database)
+	.await
+	.map_err(error::from_db(Some(&user)))?;
+
+	let body = html! {
+		details open[any_search] {
+			summary { "Filter" }
+			form method="get" {
+				label { "Actor ID" input type="number" name="actor_id" value=[search.actor_id]; }
+				label { "Action" input type="text" name="action" value=[search.action.as_deref()]; }
+				label { "Target type" input type="text" name="target_type" value=[search.target_type.as_deref()]; }
+				label { "Target ID" input type="number" name="target_id" value=[search.target_id]; }
+				div.row {
+					input type="submit" value="Filter";
+					a href="/modlog" { "Clear filter" }
+				}
+			}
+		}
+		table {
+			thead { tr {
+				th { "Time" }
+				th { "Actor" }
+				th { "Action" }
+				th { "Target" }
+				th { "Detail" }
+			} }
+			tbody { @for entry in &entries { tr {
+				td { (entry.time) }
+				td { a href={"/users/"(entry.actor)} { (entry.actor_name) } }
+				td { (entry.action.name()) }
+				td { (entry.target_type.name()) " " (entry.target_id) }
+				td { (entry.detail.as_deref().unwrap_or("")) }
+			} } }
+		}
+		@if entries.is_empty() { p { "Nothing here..." } }
+		(pagination.make_pager(num_entries, search.to_query()))
+	};
+
+	Ok(page("Moderation Log", Some(&user), &body).into_response())
+}
+
+pub fn router() -> axum::Router<Arc<State>> {
+	axum::Router::new().route("/modlog", get(handler))
+}
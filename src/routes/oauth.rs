@@ -0,0 +1,393 @@
+use std::sync::Arc;
+
+use axum::extract;
+use axum::http::HeaderMap;
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::routing::get;
+use cookie::Cookie;
+use hmac::{Hmac, Mac};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{query, query_scalar};
+
+use crate::config::OAuthProvider;
+use crate::error::ErrorResponse;
+use crate::extract::auth;
+use crate::extract::return_to::ReturnTo;
+use crate::model::{PermissionLevel, UserId};
+use crate::time::{minutes, now, Duration, Timestamp};
+use crate::util::{base64_url_no_pad, constant_time_eq};
+use crate::{password, State, CONFIG};
+
+const COOKIE_NAME: &str = "oauth_session";
+const SESSION_DURATION: Duration = minutes(10);
+
+/// Signs the short-lived cookie carrying the CSRF `state` and PKCE `code_verifier` across the redirect
+/// to the provider and back. Regenerated on every restart, which just invalidates any in-flight attempts.
+static COOKIE_KEY: Lazy<[u8; 32]> = Lazy::new(rand::random);
+
+#[derive(Serialize, Deserialize)]
+struct SessionData {
+	/// Bound into the signed cookie so a callback can't be replayed against a different provider
+	/// than the one `start` was invoked for.
+	provider: String,
+	csrf: String,
+	code_verifier: String,
+	return_to: Option<String>,
+	expiration: Timestamp,
+}
+
+fn sign(payload: &[u8]) -> [u8; 32] {
+	let mut mac =
+		<Hmac<Sha256>>::new_from_slice(&*COOKIE_KEY).expect("HMAC-SHA256 accepts keys of any length");
+	mac.update(payload);
+	mac.finalize().into_bytes().into()
+}
+
+fn encode_session(data: &SessionData) -> String {
+	let payload = serde_json::to_vec(data).expect("serializing oauth session");
+	let tag = sign(&payload);
+	format!("{}.{}", hex::encode(payload), hex::encode(tag))
+}
+
+fn decode_session(raw: &str) -> Option<SessionData> {
+	let (payload_hex, tag_hex) = raw.split_once('.')?;
+	let payload = hex::decode(payload_hex).ok()?;
+	let tag = hex::decode(tag_hex).ok()?;
+	if !constant_time_eq(&tag, &sign(&payload)) {
+		return None;
+	}
+	let data: SessionData = serde_json::from_slice(&payload).ok()?;
+	(!data.expiration.is_in_past()).then_some(data)
+}
+
+fn random_token() -> String {
+	hex::encode(rand::random::<[u8; 32]>())
+}
+
+fn redirect_uri(provider_name: &str) -> String {
+	format!("{}/oauth/{provider_name}/callback", CONFIG.external_url)
+}
+
+fn find_provider(name: &str) -> Option<&'static OAuthProvider> {
+	CONFIG.oauth.iter().find(|provider| provider.name == name)
+}
+
+fn session_cookie(headers: &HeaderMap) -> Option<Cookie<'static>> {
+	let header = headers.get("Cookie")?;
+	let header = std::str::from_utf8(header.as_bytes()).ok()?;
+	Cookie::split_parse(header)
+		.filter_map(Result::ok)
+		.find(|cookie| cookie.name() == COOKIE_NAME)
+		.map(Cookie::into_owned)
+}
+
+fn removal_cookie() -> Cookie<'static> {
+	let mut cookie = Cookie::named(COOKIE_NAME);
+	cookie.make_removal();
+	cookie
+}
+
+async fn start(
+	extract::Path(provider_name): extract::Path<String>,
+	extract::Query(return_to): extract::Query<ReturnTo>,
+) -> Result<Response, Response> {
+	let Some(provider) = find_provider(&provider_name) else {
+		return Err(ErrorResponse::bad_request("Unknown single sign-on provider.").into_response(None));
+	};
+
+	let session = SessionData {
+		provider: provider_name.clone(),
+		csrf: random_token(),
+		code_verifier: random_token(),
+		return_to: Some(return_to.path().to_owned()).filter(|path| path != "/"),
+		expiration: now() + SESSION_DURATION,
+	};
+	let code_challenge = base64_url_no_pad(&Sha256::digest(session.code_verifier.as_bytes()));
+
+	let authorize_url = format!(
+		"{authorize_url}?response_type=code&client_id={client_id}&redirect_uri={redirect_uri}&scope={scope}&state={state}&code_challenge={code_challenge}&code_challenge_method=S256",
+		authorize_url = provider.authorize_url,
+		client_id = crate::util::encode_query(provider.client_id.as_bytes()),
+		redirect_uri = crate::util::encode_query(redirect_uri(&provider_name).as_bytes()),
+		scope = crate::util::encode_query(provider.scopes.join(" ").as_bytes()),
+		state = session.csrf,
+	);
+
+	let cookie = Cookie::build(COOKIE_NAME, encode_session(&session))
+		.secure(true)
+		.http_only(true)
+		.same_site(cookie::SameSite::Lax)
+		.max_age(cookie::time::Duration::seconds(SESSION_DURATION))
+		.finish();
+
+	let mut response = Redirect::to(&authorize_url).into_response();
+	response
+		.headers_mut()
+		.insert("Set-Cookie", cookie.encoded().to_string().try_into().unwrap());
+	Ok(response)
+}
+
+#[derive(Deserialize)]
+struct CallbackQuery {
+	code: Option<String>,
+	state: Option<String>,
+	error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+	access_token: String,
+}
+
+#[derive(Deserialize)]
+struct UserInfo {
+	sub: String,
+	email: Option<String>,
+	#[serde(default)]
+	email_verified: bool,
+	#[serde(default)]
+	name: Option<String>,
+	#[serde(default)]
+	preferred_username: Option<String>,
+}
+
+fn sanitize_username(raw: &str) -> String {
+	let sanitized: String = raw
+		.chars()
+		.filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+		.map(|c| c.to_ascii_lowercase())
+		.collect();
+	if sanitized.is_empty() {
+		"user".into()
+	} else {
+		sanitized
+	}
+}
+
+/// Creates a local account for a first-time single sign-on user. Retries with a numeric
+/// suffix on a username collision, the same way [`auth::log_in`] retries on a token collision.
+async fn provision_user(state: &State, userinfo: &UserInfo) -> Result<UserId, ErrorResponse> {
+	let base_username = sanitize_username(
+		userinfo
+			.preferred_username
+			.as_deref()
+			.unwrap_or(&userinfo.sub),
+	);
+	let display_name = userinfo
+		.name
+		.clone()
+		.or_else(|| userinfo.preferred_username.clone())
+		.unwrap_or_else(|| base_username.clone());
+	let creation_time = now();
+	let permission_level = PermissionLevel::default();
+	// The user never sees or needs this; they always log in via single sign-on.
+	let placeholder_password = password::hash(&random_token());
+
+	let mut attempt: u32 = 0;
+	loop {
+		let username = if attempt == 0 {
+			base_username.clone()
+		} else {
+			format!("{base_username}{attempt}")
+		};
+		let res = query_scalar!(
+			"insert into users (username, display_name, email, password, creation_time, permission_level) values (?, ?, ?, ?, ?, ?) returning id",
+			username,
+			display_name,
+			userinfo.email,
+			placeholder_password,
+			creation_time,
+			permission_level,
+		)
+		.fetch_one(&state.database)
+		.await;
+		match res {
+			Err(sqlx::Error::Database(error))
+				if error.kind() == sqlx::error::ErrorKind::UniqueViolation =>
+			{
+				attempt += 1;
+				continue;
+			}
+			Err(error) => return Err(ErrorResponse::from_db(error)),
+			Ok(id) => return Ok(id),
+		}
+	}
+}
+
+/// Returns `true` if `userinfo` is allowed to auto-provision a new account under `provider`,
+/// i.e. `provider` has no domain allow-list, or `userinfo`'s verified email matches it.
+fn may_provision(provider: &OAuthProvider, userinfo: &UserInfo) -> bool {
+	let Some(allowed_domains) = &provider.allowed_email_domains else {
+		return true;
+	};
+	userinfo.email_verified
+		&& userinfo
+			.email
+			.as_deref()
+			.and_then(|email| email.rsplit_once('@'))
+			.is_some_and(|(_, domain)| {
+				allowed_domains
+					.iter()
+					.any(|allowed| allowed.eq_ignore_ascii_case(domain))
+			})
+}
+
+/// Maps the provider's subject to a `UserId`, linking to an existing account by verified email
+/// or provisioning a new one, and remembers the mapping in `oauth_identities` for next time.
+async fn resolve_user(
+	state: &State,
+	provider: &OAuthProvider,
+	userinfo: &UserInfo,
+) -> Result<UserId, ErrorResponse> {
+	if let Some(user_id) = query_scalar!(
+		r#"select user as "user: UserId" from oauth_identities where provider = ? and subject = ?"#,
+		provider.name,
+		userinfo.sub,
+	)
+	.fetch_optional(&state.database)
+	.await
+	.map_err(ErrorResponse::internal)?
+	{
+		return Ok(user_id);
+	}
+
+	let existing_user = if userinfo.email_verified {
+		match &userinfo.email {
+			Some(email) => query_scalar!(r#"select id as "id: UserId" from users where email = ?"#, email)
+				.fetch_optional(&state.database)
+				.await
+				.map_err(ErrorResponse::internal)?,
+			None => None,
+		}
+	} else {
+		None
+	};
+
+	let user_id = match existing_user {
+		Some(user_id) => user_id,
+		None => {
+			if !may_provision(provider, userinfo) {
+				return Err(ErrorResponse::bad_request(
+					"Your email address is not allowed to sign in via this provider.",
+				));
+			}
+			provision_user(state, userinfo).await?
+		}
+	};
+
+	query!(
+		"insert into oauth_identities (provider, subject, user) values (?, ?, ?)",
+		provider.name,
+		userinfo.sub,
+		user_id,
+	)
+	.execute(&state.database)
+	.await
+	.map_err(ErrorResponse::internal)?;
+
+	Ok(user_id)
+}
+
+async fn callback(
+	extract::State(state): extract::State<Arc<State>>,
+	extract::Path(provider_name): extract::Path<String>,
+	extract::Query(params): extract::Query<CallbackQuery>,
+	headers: HeaderMap,
+) -> Result<Response, Response> {
+	let Some(provider) = find_provider(&provider_name) else {
+		return Err(ErrorResponse::bad_request("Unknown single sign-on provider.").into_response(None));
+	};
+
+	let Some(session) = session_cookie(&headers).and_then(|cookie| decode_session(cookie.value()))
+	else {
+		return Err(ErrorResponse::bad_request(
+			"Your single sign-on attempt has expired. Please try again.",
+		)
+		.into_response(None));
+	};
+
+	if session.provider != provider_name {
+		return Err(
+			ErrorResponse::bad_request("Single sign-on provider mismatch; please try again.")
+				.into_response(None),
+		);
+	}
+
+	if let Some(error) = &params.error {
+		return Err(
+			ErrorResponse::bad_request(format!("Single sign-on failed: {error}")).into_response(None),
+		);
+	}
+
+	let (Some(code), Some(returned_state)) = (params.code, params.state) else {
+		return Err(ErrorResponse::bad_request(
+			"Single sign-on response was missing required parameters.",
+		)
+		.into_response(None));
+	};
+
+	if !constant_time_eq(returned_state.as_bytes(), session.csrf.as_bytes()) {
+		return Err(
+			ErrorResponse::bad_request("Single sign-on state mismatch; please try again.")
+				.into_response(None),
+		);
+	}
+
+	let internal_error = |error: reqwest::Error| ErrorResponse::internal(error).into_response(None);
+
+	let redirect_uri = redirect_uri(&provider_name);
+	let token_response: TokenResponse = OAUTH_CLIENT
+		.post(&provider.token_url)
+		.form(&[
+			("grant_type", "authorization_code"),
+			("code", &code),
+			("redirect_uri", &redirect_uri),
+			("client_id", &provider.client_id),
+			("client_secret", &provider.client_secret),
+			("code_verifier", &session.code_verifier),
+		])
+		.send()
+		.await
+		.and_then(reqwest::Response::error_for_status)
+		.map_err(internal_error)?
+		.json()
+		.await
+		.map_err(internal_error)?;
+
+	let userinfo: UserInfo = OAUTH_CLIENT
+		.get(&provider.userinfo_url)
+		.bearer_auth(&token_response.access_token)
+		.send()
+		.await
+		.and_then(reqwest::Response::error_for_status)
+		.map_err(internal_error)?
+		.json()
+		.await
+		.map_err(internal_error)?;
+
+	let user_id = resolve_user(&state, provider, &userinfo)
+		.await
+		.map_err(|error| error.into_response(None))?;
+
+	let token = auth::log_in(&state, user_id, &headers)
+		.await
+		.map_err(|error| error.into_response(None))?;
+
+	let mut response =
+		(&token, Redirect::to(session.return_to.as_deref().unwrap_or("/"))).into_response();
+	response.headers_mut().insert(
+		"Set-Cookie",
+		removal_cookie().encoded().to_string().try_into().unwrap(),
+	);
+	Ok(response)
+}
+
+static OAUTH_CLIENT: Lazy<reqwest::Client> = Lazy::new(reqwest::Client::new);
+
+pub fn router() -> axum::Router<Arc<State>> {
+	axum::Router::new()
+		.route("/oauth/:provider/start", get(start))
+		.route("/oauth/:provider/callback", get(callback))
+}
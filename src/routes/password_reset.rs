@@ -18,12 +18,12 @@ use crate::template::{page, BannerKind};
 use crate::time::{minutes, now, Timestamp};
 use crate::State;
 
-type Key = i64;
+pub(crate) type Key = i64;
 
 #[derive(Deserialize)]
-struct ActionQuery {
-	user: UserId,
-	key: Key,
+pub(crate) struct ActionQuery {
+	pub(crate) user: UserId,
+	pub(crate) key: Key,
 }
 
 async fn remove_email(
@@ -109,6 +109,62 @@ async fn do_reset(
 	Ok(page("Reset Password", login_user.as_ref(), &body).into_response())
 }
 
+/// Rotates the user's `email_verify_key`, marks their email as unverified, and sends a
+/// confirmation link to it. Called whenever an email address is set or changed.
+pub(crate) async fn send_verification_email(
+	state: &State,
+	user_id: UserId,
+	display_name: &str,
+	email: &str,
+) -> Result<(), ErrorResponse> {
+	let smtp = &crate::CONFIG.smtp;
+
+	let email_verify_key: Key = rand::random();
+	query!(
+		"update users set email_verified = false, email_verify_key = ? where id = ?",
+		email_verify_key,
+		user_id,
+	)
+	.execute(&state.database)
+	.await
+	.map_err(ErrorResponse::internal)?;
+
+	let body = format!(
+		"\
+Please confirm that this email address belongs to you by going to <https://{url}/email/verify?user={user_id}&key={email_verify_key}>.
+
+If you did not expect this email, you can ignore it.
+",
+		url = crate::CONFIG.external_url,
+	);
+
+	let display_name = display_name.to_owned();
+	let email = email.to_owned();
+	let send_fut = async move {
+		let message = MessageBuilder::new()
+			.from(("Autograder", smtp.username.as_str()))
+			.to((display_name.as_str(), email.as_str()))
+			.subject("AutoGrader Email Verification")
+			.text_body(body);
+
+		SmtpClientBuilder::new(smtp.host.as_str(), smtp.port)
+			.helo_host("dummy.faircode.eu")
+			.implicit_tls(smtp.implicit_tls)
+			.credentials((smtp.username.as_str(), smtp.password.as_str()))
+			.connect()
+			.await?
+			.send(message)
+			.await
+	};
+	tokio::spawn(async move {
+		if let Err(error) = send_fut.await {
+			tracing::error!(user=?user_id, "error sending verification email: {error}");
+		}
+	});
+
+	Ok(())
+}
+
 #[derive(serde::Deserialize, Debug)]
 struct Form {
 	username: String,
@@ -118,7 +174,7 @@ async fn handle_post(state: &State, post: Form) -> Result<(), ErrorResponse> {
 	let smtp = &crate::CONFIG.smtp;
 
 	let user = query!(
-		r#"select id as "id!", display_name, email, remove_email_key, password_reset_expiration as "password_reset_expiration: Timestamp" from users where username = ?"#,
+		r#"select id as "id!", display_name, email, remove_email_key, email_verified as "email_verified!: bool", password_reset_expiration as "password_reset_expiration: Timestamp" from users where username = ?"#,
 		post.username,
 	)
 	.fetch_optional(&state.database)
@@ -131,6 +187,11 @@ async fn handle_post(state: &State, post: Form) -> Result<(), ErrorResponse> {
 	let Some(user_email) = user.email else {
 		return Ok(());
 	};
+	// Unverified addresses can't receive reset mail, or a reset link could be weaponized to spam
+	// (and potentially take over) an address the account owner doesn't actually control.
+	if !user.email_verified {
+		return Ok(());
+	}
 
 	if user
 		.password_reset_expiration
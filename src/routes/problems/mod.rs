@@ -13,8 +13,8 @@ use crate::template::page;
 use crate::util::{s, search_query};
 use crate::{error, State};
 
-mod new;
-mod problem;
+pub(crate) mod new;
+pub(crate) mod problem;
 
 const DEFAULT_PAGE_SIZE: u32 = 20;
 
@@ -56,7 +56,7 @@ async fn handler(
 	}
 	.fetch_one(&state.database)
 	.await
-	.map_err(error::sqlx(user.as_ref()))?;
+	.map_err(error::from_db(user.as_ref()))?;
 
 	let user_id = user.as_ref().map(|user| user.id);
 	let problems = query!(
@@ -70,7 +70,7 @@ async fn handler(
 		search.created_by_id,
 		search.solved_by,
 		search.solved_by_id,
-	).fetch_all(&state.database).await.map_err(error::sqlx(user.as_ref()))?;
+	).fetch_all(&state.database).await.map_err(error::from_db(user.as_ref()))?;
 
 	let body = html! {
 		@if user.as_ref().is_some_and(|user| user.permission_level >= PermissionLevel::ProblemAuthor) {
@@ -7,13 +7,15 @@ use axum::routing::get;
 use maud::html;
 use sqlx::query_scalar;
 
+use crate::blob_store::BlobStore;
 use crate::error::ErrorResponse;
 use crate::extract::auth::{ProblemAuthor, User};
+use crate::extract::csrf::Csrf;
 use crate::extract::if_post::IfPost;
 use crate::model::{ProblemId, Tests};
-use crate::template::{page, BannerKind};
+use crate::template::{csrf_query, page, BannerKind};
 use crate::time::now;
-use crate::util::deserialize_textarea;
+use crate::util::{deserialize_textarea, normalize_textarea};
 use crate::State;
 
 fn deserialize_optional_textarea<'de, D: serde::de::Deserializer<'de>>(
@@ -59,7 +61,8 @@ def judge(i: int, case_input: str, expected_output: str, actual_output: str) ->
   return actual_output == expected_output
 ";
 
-async fn handle_post(
+/// Inserts a new problem. Shared by the HTML problem-creation form and the JSON API.
+pub(crate) async fn handle_post(
 	state: &State,
 	user: &User,
 	post: &Problem,
@@ -76,11 +79,88 @@ async fn handle_post(
 			})?;
 	}
 
+	let tests_key = state
+		.blobs
+		.put(post.tests.as_bytes())
+		.await
+		.map_err(ErrorResponse::internal)?;
+
 	let now = now();
-	let id = query_scalar!("insert into problems (name, description, time_limit, visible, tests, custom_judger, creation_time, created_by) values (?, ?, ?, ?, ?, ?, ?, ?) returning id", post.name, post.description, post.time_limit, post.visible, post.tests, post.custom_judger, now, user.id).fetch_one(&state.database).await.map_err(ErrorResponse::sqlx)?;
+	let id = query_scalar!("insert into problems (name, description, time_limit, visible, tests, custom_judger, creation_time, created_by) values (?, ?, ?, ?, ?, ?, ?, ?) returning id", post.name, post.description, post.time_limit, post.visible, tests_key, post.custom_judger, now, user.id).fetch_one(&state.database).await.map_err(ErrorResponse::from_db)?;
 	Ok(id)
 }
 
+/// Builds a `Problem` from a multipart POST to the new-problem form. Test cases come either from
+/// the pasted `tests` textarea or, if present, a `tests_archive` ZIP of `{n}.in`/`{n}.out` pairs
+/// (see [`super::problem::parse_cases_zip`]); the archive takes priority if both are given.
+async fn problem_from_multipart(
+	mut multipart: extract::Multipart,
+) -> Result<Problem, ErrorResponse> {
+	let mut name = None;
+	let mut description = String::new();
+	let mut time_limit = None;
+	let mut visible = false;
+	let mut tests = None;
+	let mut tests_archive = None;
+	let mut custom_judger = None;
+
+	while let Some(field) = multipart
+		.next_field()
+		.await
+		.map_err(ErrorResponse::internal)?
+	{
+		match field.name() {
+			Some("name") => name = Some(field.text().await.map_err(ErrorResponse::internal)?),
+			Some("description") => {
+				description = normalize_textarea(&field.text().await.map_err(ErrorResponse::internal)?);
+			}
+			Some("time_limit") => {
+				let raw = field.text().await.map_err(ErrorResponse::internal)?;
+				time_limit = Some(raw.trim().parse().map_err(|_| {
+					ErrorResponse::bad_request("Time limit must be a whole number.")
+				})?);
+			}
+			Some("visible") => visible = true,
+			Some("tests") => {
+				let raw = normalize_textarea(&field.text().await.map_err(ErrorResponse::internal)?);
+				if !raw.is_empty() {
+					tests = Some(raw);
+				}
+			}
+			Some("tests_archive") => {
+				let bytes = field.bytes().await.map_err(ErrorResponse::internal)?;
+				if !bytes.is_empty() {
+					tests_archive = Some(bytes);
+				}
+			}
+			Some("custom_judger") => {
+				let raw = normalize_textarea(&field.text().await.map_err(ErrorResponse::internal)?);
+				custom_judger = Some(raw).filter(|raw| !raw.is_empty());
+			}
+			_ => {}
+		}
+	}
+
+	let tests = match tests_archive {
+		Some(bytes) => {
+			super::problem::parse_cases_zip(&bytes).map_err(ErrorResponse::bad_request)?
+		}
+		None => tests.ok_or_else(|| {
+			ErrorResponse::bad_request("Tests are required: paste them, or upload a ZIP archive.")
+		})?,
+	};
+
+	Ok(Problem {
+		name: name.ok_or_else(|| ErrorResponse::bad_request("Name is required."))?,
+		description,
+		time_limit: time_limit
+			.ok_or_else(|| ErrorResponse::bad_request("Time limit is required."))?,
+		visible,
+		tests,
+		custom_judger,
+	})
+}
+
 pub fn problem_form(old: Option<&Problem>) -> maud::Markup {
 	html! {
 		label {
@@ -101,7 +181,7 @@ pub fn problem_form(old: Option<&Problem>) -> maud::Markup {
 		}
 		label {
 			"Tests"
-			textarea required name="tests" placeholder=(EXAMPLE_TESTS) rows="15" cols="35" { (old.map_or("", |post| post.tests.trim())) }
+			textarea name="tests" placeholder=(EXAMPLE_TESTS) rows="15" cols="35" { (old.map_or("", |post| post.tests.trim())) }
 		}
 		details {
 			summary { "How to write tests" }
@@ -113,6 +193,16 @@ pub fn problem_form(old: Option<&Problem>) -> maud::Markup {
 				p { "The first test case will be shown as an example on the problem page." }
 			}
 		}
+		label {
+			"Or upload a ZIP of test cases (optional, new problems only; replaces the pasted tests above)"
+			input type="file" name="tests_archive" accept=".zip";
+		}
+		details {
+			summary { "How to format the ZIP" }
+			div.details {
+				p { "Pair each case's input and output files by a shared numeric stem, e.g. " code { "01.in" } "/" code { "01.out" } ", " code { "02.in" } "/" code { "02.out" } "." }
+			}
+		}
 		label {
 			"Custom Judger"
 			textarea name="custom_judger" placeholder="(Empty = normal judging)" rows="15" cols="35" { (old.and_then(|post| post.custom_judger.as_deref()).map_or("", str::trim)) }
@@ -134,22 +224,29 @@ pub fn problem_form(old: Option<&Problem>) -> maud::Markup {
 async fn handler(
 	extract::State(state): extract::State<Arc<State>>,
 	ProblemAuthor(user): ProblemAuthor,
-	IfPost(post): IfPost<extract::Form<Problem>>,
+	IfPost(multipart): IfPost<Csrf<extract::Multipart>>,
 ) -> Response {
-	let post = post.map(|extract::Form(post)| post);
-	let post = post.as_ref();
+	let mut post = None;
 
-	let error = if let Some(post) = post {
-		match handle_post(&state, &user, post).await {
-			Ok(id) => return Redirect::to(&format!("/problem/{id}")).into_response(),
+	let error = if let Some(Csrf(multipart)) = multipart {
+		match problem_from_multipart(multipart).await {
+			Ok(parsed) => {
+				let result = handle_post(&state, &user, &parsed).await;
+				post = Some(parsed);
+				match result {
+					Ok(id) => return Redirect::to(&format!("/problem/{id}")).into_response(),
+					Err(error) => Some(error),
+				}
+			}
 			Err(error) => Some(error),
 		}
 	} else {
 		None
 	};
+	let post = post.as_ref();
 
 	let body = html! {
-		form method="post" {
+		form method="post" enctype="multipart/form-data" action={"/problems/new?"(csrf_query(&user))} {
 			(problem_form(post))
 			input type="submit" value="Create";
 		}
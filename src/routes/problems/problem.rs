@@ -1,4 +1,5 @@
-use std::io::Write;
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
 use std::sync::Arc;
 
 use axum::extract;
@@ -7,27 +8,38 @@ use axum::response::{IntoResponse, Redirect, Response};
 use axum::routing::{get, post};
 use maud::html;
 use serde::Deserialize;
-use sqlx::{query, query_as, query_scalar};
+use sqlx::{query, query_scalar};
 
 use super::new::problem_form;
 use super::pass_rate;
+use crate::blob_store::{BlobKey, BlobStore};
 use crate::error::ErrorResponse;
 use crate::extract::auth::User;
+use crate::extract::csrf::Csrf;
 use crate::extract::if_post::IfPost;
-use crate::model::{Language, PermissionLevel, ProblemId, Tests, UserId};
-use crate::template::{page, BannerKind};
+use crate::mod_log;
+use crate::model::{
+	join_cases, JudgeStatus, Language, PermissionLevel, ProblemId, SubmissionId, Tests, UserId,
+};
+use crate::ratelimit;
+use crate::template::{csrf_field, csrf_query, page, BannerKind};
 use crate::time::{now, Timestamp};
 use crate::util::{deserialize_textarea, s};
 use crate::{error, State};
 
+/// Body of a POST form that carries no data of its own, only the `_csrf` field checked by
+/// [`Csrf`].
+#[derive(Debug, Deserialize)]
+struct EmptyForm {}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-enum ProblemPermissionLevel {
+pub(crate) enum ProblemPermissionLevel {
 	None,
 	View,
 	Edit,
 }
 
-fn permission_level(
+pub(crate) fn permission_level(
 	user: Option<&User>,
 	problem_created_by: Option<UserId>,
 	problem_visible: bool,
@@ -48,6 +60,7 @@ fn permission_level(
 
 async fn handle_edit_post(
 	state: &State,
+	actor: UserId,
 	problem_id: ProblemId,
 	post: &super::new::Problem,
 ) -> Result<(), ErrorResponse> {
@@ -63,7 +76,25 @@ async fn handle_edit_post(
 			})?;
 	}
 
-	query!("update problems set name = ?, description = ?, time_limit = ?, visible = ?, tests = ?, custom_judger = ? where id = ?", post.name, post.description, post.time_limit, post.visible, post.tests, post.custom_judger, problem_id).execute(&state.database).await.map_err(ErrorResponse::sqlx)?;
+	let tests_key = state
+		.blobs
+		.put(post.tests.as_bytes())
+		.await
+		.map_err(ErrorResponse::internal)?;
+
+	query!("update problems set name = ?, description = ?, time_limit = ?, visible = ?, tests = ?, custom_judger = ? where id = ?", post.name, post.description, post.time_limit, post.visible, tests_key, post.custom_judger, problem_id).execute(&state.database).await.map_err(ErrorResponse::from_db)?;
+
+	let detail = format!(r#"{{"name":{:?}}}"#, post.name);
+	mod_log::record(
+		state,
+		actor,
+		mod_log::Action::EditProblem,
+		mod_log::TargetType::Problem,
+		problem_id,
+		Some(&detail),
+	)
+	.await
+	.map_err(ErrorResponse::from_db)?;
 
 	Ok(())
 }
@@ -72,7 +103,7 @@ async fn edit_handler(
 	extract::State(state): extract::State<Arc<State>>,
 	user: Option<User>,
 	extract::Path(problem_id): extract::Path<ProblemId>,
-	IfPost(post): IfPost<extract::Form<super::new::Problem>>,
+	IfPost(post): IfPost<Csrf<extract::Form<super::new::Problem>>>,
 ) -> Result<Response, Response> {
 	let Some(problem) = query!(
 		r#"select created_by, visible as "visible: bool" from problems where id = ?"#,
@@ -80,7 +111,7 @@ async fn edit_handler(
 	)
 	.fetch_optional(&state.database)
 	.await
-	.map_err(error::sqlx(user.as_ref()))?
+	.map_err(error::from_db(user.as_ref()))?
 	else {
 		return Err(error::not_found(user.as_ref()).await);
 	};
@@ -90,31 +121,59 @@ async fn edit_handler(
 		return Err(error::fake_not_found(user.as_ref()).await);
 	}
 
-	let post_res = if let Some(post) = post {
-		Some(handle_edit_post(&state, problem_id, &post).await)
+	let post_res = if let Some(Csrf(post)) = post {
+		let actor = user.as_ref().expect("Edit implies a logged-in user").id;
+		Some(handle_edit_post(&state, actor, problem_id, &post).await)
 	} else {
 		None
 	};
 
-	let Some(problem) = query_as!(
-		super::new::Problem,
-		r#"select name, description, time_limit as "time_limit: u32", visible as "visible: bool", tests, custom_judger from problems inner join users on problems.created_by = users.id where problems.id = ?"#,
+	let Some(row) = query!(
+		r#"select name, description, time_limit as "time_limit: u32", visible as "visible: bool", tests as "tests: BlobKey", custom_judger from problems inner join users on problems.created_by = users.id where problems.id = ?"#,
 		problem_id,
 	)
 	.fetch_optional(&state.database)
 	.await
-	.map_err(error::sqlx(user.as_ref()))?
+	.map_err(error::from_db(user.as_ref()))?
 	else {
 		return Err(error::not_found(user.as_ref()).await);
 	};
 
+	let tests = state
+		.blobs
+		.get(row.tests)
+		.await
+		.map_err(error::internal(user.as_ref()))?;
+	let tests = String::from_utf8(tests).map_err(error::internal(user.as_ref()))?;
+
+	let problem = super::new::Problem {
+		name: row.name,
+		description: row.description,
+		time_limit: row.time_limit,
+		visible: row.visible,
+		tests,
+		custom_judger: row.custom_judger,
+	};
+
+	let edit_user = user.as_ref().expect("Edit implies a logged-in user");
 	let title = format!("Edit Problem {problem_id}");
 	let body = html! {
 		p { a href={"/problem/"(problem_id)} { "Back to problem page" } }
 		form method="post" {
+			(csrf_field(edit_user))
 			(problem_form(Some(&problem)))
 			input type="submit" value="Edit";
 		}
+		details {
+			summary { "Replace tests from a ZIP file" }
+			div.details {
+				p { "Upload a ZIP in the same " code { "{i}.in" } "/" code { "{i}.out" } " layout produced by " a href={"/problem/"(problem_id)"/cases"} { "Download cases" } ". This replaces the existing tests." }
+				form method="post" action={"/problem/"(problem_id)"/cases?"(csrf_query(edit_user))} enctype="multipart/form-data" {
+					input type="file" name="cases" accept=".zip" required;
+					input type="submit" value="Upload";
+				}
+			}
+		}
 	};
 
 	let status = post_res
@@ -134,6 +193,7 @@ async fn delete_handler(
 	extract::State(state): extract::State<Arc<State>>,
 	user: Option<User>,
 	extract::Path(problem_id): extract::Path<ProblemId>,
+	Csrf(extract::Form(EmptyForm {})): Csrf<extract::Form<EmptyForm>>,
 ) -> Result<Response, Response> {
 	let Some(problem) = query!(
 		r#"select created_by, visible as "visible: bool" from problems where id = ?"#,
@@ -141,7 +201,7 @@ async fn delete_handler(
 	)
 	.fetch_optional(&state.database)
 	.await
-	.map_err(error::sqlx(user.as_ref()))?
+	.map_err(error::from_db(user.as_ref()))?
 	else {
 		return Err(error::not_found(user.as_ref()).await);
 	};
@@ -154,7 +214,19 @@ async fn delete_handler(
 	query!("delete from problems where id = ?", problem_id)
 		.execute(&state.database)
 		.await
-		.map_err(error::sqlx(user.as_ref()))?;
+		.map_err(error::from_db(user.as_ref()))?;
+
+	let actor = user.as_ref().expect("Edit implies a logged-in user").id;
+	mod_log::record(
+		&state,
+		actor,
+		mod_log::Action::DeleteProblem,
+		mod_log::TargetType::Problem,
+		problem_id,
+		None,
+	)
+	.await
+	.map_err(error::from_db(user.as_ref()))?;
 
 	Ok(Redirect::to("/problems").into_response())
 }
@@ -165,12 +237,12 @@ async fn download_cases(
 	extract::Path(problem_id): extract::Path<ProblemId>,
 ) -> Result<Response, Response> {
 	let Some(problem) = query!(
-		r#"select tests as "tests: Tests", created_by, visible as "visible: bool" from problems where id = ?"#,
+		r#"select tests as "tests: BlobKey", created_by, visible as "visible: bool" from problems where id = ?"#,
 		problem_id,
 	)
 	.fetch_optional(&state.database)
 	.await
-	.map_err(error::sqlx(user.as_ref()))?
+	.map_err(error::from_db(user.as_ref()))?
 	else {
 		return Err(error::not_found(user.as_ref()).await);
 	};
@@ -181,12 +253,20 @@ async fn download_cases(
 		return Err(error::fake_not_found(user.as_ref()).await);
 	}
 
+	let tests = state
+		.blobs
+		.get(problem.tests)
+		.await
+		.map_err(error::internal(user.as_ref()))?;
+	let tests = String::from_utf8(tests).map_err(error::internal(user.as_ref()))?;
+	let tests = Tests::try_from(tests).map_err(error::internal(user.as_ref()))?;
+
 	let mut zip_buf = Vec::new();
 	let mut zip = zip::ZipWriter::new(std::io::Cursor::new(&mut zip_buf));
 	let zip_options =
 		zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
 
-	for (i, (input, output)) in problem.tests.cases().enumerate() {
+	for (i, (input, output)) in tests.cases().enumerate() {
 		let i = i + 1;
 
 		zip.start_file(format!("{i}.in"), zip_options).unwrap();
@@ -198,6 +278,18 @@ async fn download_cases(
 	zip.finish().unwrap();
 	drop(zip);
 
+	let actor = user.as_ref().expect("Edit implies a logged-in user").id;
+	mod_log::record(
+		&state,
+		actor,
+		mod_log::Action::DownloadCases,
+		mod_log::TargetType::Problem,
+		problem_id,
+		None,
+	)
+	.await
+	.map_err(error::from_db(user.as_ref()))?;
+
 	let content_disposition = format!("attachment; filename=\"{problem_id}.zip\"");
 	let response = (
 		[
@@ -209,11 +301,165 @@ async fn download_cases(
 	Ok(response.into_response())
 }
 
+fn normalize_case_text(raw: &str) -> String {
+	raw.trim().replace("\r\n", "\n")
+}
+
+/// Parses a ZIP in the `{i}.in`/`{i}.out` layout produced by `download_cases` back into the raw
+/// test format, pairing files by their numeric stem and sorting by case number.
+pub(crate) fn parse_cases_zip(bytes: &[u8]) -> Result<String, String> {
+	let mut zip = zip::ZipArchive::new(std::io::Cursor::new(bytes))
+		.map_err(|error| format!("Could not read the uploaded file as a ZIP archive: {error}"))?;
+
+	let mut cases: BTreeMap<u64, (Option<String>, Option<String>)> = BTreeMap::new();
+
+	for i in 0..zip.len() {
+		let mut file = zip
+			.by_index(i)
+			.map_err(|error| format!("Could not read an entry in the ZIP archive: {error}"))?;
+		if file.is_dir() {
+			continue;
+		}
+
+		let name = file.name().to_owned();
+		let (stem, extension) = name
+			.rsplit_once('.')
+			.ok_or_else(|| format!("{name:?} has no extension; expected `.in` or `.out`."))?;
+		let index: u64 = stem
+			.parse()
+			.map_err(|_error| format!("{name:?} is not named with a case number."))?;
+
+		let mut contents = String::new();
+		file
+			.read_to_string(&mut contents)
+			.map_err(|error| format!("{name:?} is not valid UTF-8: {error}"))?;
+		let contents = normalize_case_text(&contents);
+
+		let entry = cases.entry(index).or_default();
+		match extension {
+			"in" => entry.0 = Some(contents),
+			"out" => entry.1 = Some(contents),
+			_ => return Err(format!("{name:?} has an unrecognized extension; expected `.in` or `.out`.")),
+		}
+	}
+
+	if cases.is_empty() {
+		return Err("The ZIP archive contains no test case files.".into());
+	}
+
+	let cases = cases
+		.into_iter()
+		.map(|(index, (input, output))| {
+			let input = input.ok_or_else(|| format!("Case {index} is missing its `.in` file."))?;
+			let output = output.ok_or_else(|| format!("Case {index} is missing its `.out` file."))?;
+			Ok((input, output))
+		})
+		.collect::<Result<Vec<_>, String>>()?;
+
+	Ok(join_cases(
+		cases.iter().map(|(input, output)| (input.as_str(), output.as_str())),
+	))
+}
+
+async fn upload_cases(
+	extract::State(state): extract::State<Arc<State>>,
+	user: Option<User>,
+	extract::Path(problem_id): extract::Path<ProblemId>,
+	Csrf(mut upload): Csrf<extract::Multipart>,
+) -> Result<Response, Response> {
+	let Some(row) = query!(
+		r#"select name, description, time_limit as "time_limit: u32", visible as "visible: bool", custom_judger, created_by from problems where id = ?"#,
+		problem_id,
+	)
+	.fetch_optional(&state.database)
+	.await
+	.map_err(error::from_db(user.as_ref()))?
+	else {
+		return Err(error::not_found(user.as_ref()).await);
+	};
+
+	if permission_level(user.as_ref(), row.created_by, row.visible) < ProblemPermissionLevel::Edit {
+		return Err(error::fake_not_found(user.as_ref()).await);
+	}
+
+	let Some(field) = upload
+		.next_field()
+		.await
+		.map_err(error::internal(user.as_ref()))?
+	else {
+		return Err(ErrorResponse::bad_request("No file was uploaded.").into_response(user.as_ref()));
+	};
+	let zip_bytes = field.bytes().await.map_err(error::internal(user.as_ref()))?;
+
+	let tests = parse_cases_zip(&zip_bytes)
+		.map_err(|message| ErrorResponse::bad_request(message).into_response(user.as_ref()))?;
+	Tests::validate(&tests)
+		.map_err(|error| ErrorResponse::bad_request(error.to_string()).into_response(user.as_ref()))?;
+
+	let post = super::new::Problem {
+		name: row.name,
+		description: row.description,
+		time_limit: row.time_limit,
+		visible: row.visible,
+		tests,
+		custom_judger: row.custom_judger,
+	};
+
+	let actor = user.as_ref().expect("Edit implies a logged-in user").id;
+	handle_edit_post(&state, actor, problem_id, &post)
+		.await
+		.map_err(|error| error.into_response(user.as_ref()))?;
+
+	Ok(Redirect::to(&format!("/problem/{problem_id}/edit")).into_response())
+}
+
 #[derive(Debug, Deserialize)]
-struct Post {
-	language: Language,
+pub(crate) struct Post {
+	pub language: Language,
 	#[serde(deserialize_with = "deserialize_textarea")]
-	code: String,
+	pub code: String,
+}
+
+/// Inserts a new submission and hands it to the judge queue. Shared by the HTML submission form and the JSON API.
+pub(crate) async fn submit(
+	state: &State,
+	user: &User,
+	problem_id: ProblemId,
+	post: &Post,
+) -> Result<SubmissionId, ErrorResponse> {
+	if user.permission_level < PermissionLevel::Admin {
+		if let ratelimit::Decision::Denied { retry_after } = state.ratelimit.check(user.id) {
+			let seconds = retry_after.as_secs().max(1);
+			return Err(ErrorResponse::too_many_requests(
+				format!(
+					"You're submitting too quickly. Please wait {seconds} second{} and try again.",
+					s(seconds.try_into().unwrap_or(i64::MAX)),
+				),
+				seconds,
+			));
+		}
+	}
+
+	let code_key = state
+		.blobs
+		.put(post.code.as_bytes())
+		.await
+		.map_err(ErrorResponse::internal)?;
+
+	let now = now();
+	let submission_id = query_scalar!(
+		"insert into submissions (code, for_problem, submitter, language, submission_time, result, judge_status) values (?, ?, ?, ?, ?, null, ?) returning id",
+		code_key,
+		problem_id,
+		user.id,
+		post.language,
+		now,
+		JudgeStatus::Queued,
+	).fetch_one(&state.database).await.map_err(ErrorResponse::from_db)?;
+
+	crate::routes::submissions::enqueue_for_judging(state, submission_id).await?;
+
+	Ok(submission_id)
 }
 
 async fn handle_post(
@@ -226,13 +472,13 @@ async fn handle_post(
 		return Err(ErrorResponse {
 			status: StatusCode::UNAUTHORIZED,
 			message: "You must be logged in to make submissions.".into(),
+			retry_after: None,
 		});
 	};
 
-	let now = now();
-	let submission_id = query_scalar!("insert into submissions (code, for_problem, submitter, language, submission_time, result) values (?, ?, ?, ?, ?, null) returning id", post.code, problem_id, user.id, post.language, now).fetch_one(&state.database).await.map_err(ErrorResponse::sqlx)?;
+	let submission_id = submit(state, user, problem_id, post).await?;
 
-	crate::routes::submissions::do_judge(state, submission_id).await
+	Ok(Redirect::to(&format!("/submission/{submission_id}")).into_response())
 }
 
 async fn handler(
@@ -252,13 +498,13 @@ async fn handler(
 
 	let user_id = user.as_ref().map(|user| user.id);
 	let Some(problem) = query!(
-		r#"select name, description, problems.creation_time as "creation_time: Timestamp", users.id as "created_by_id?", users.display_name as "created_by_name?", (select count(*) from submissions where for_problem = problems.id) as "num_submissions!: i64", (select count(*) from submissions where for_problem = problems.id and result like 'o%') as "num_correct_submissions!: i64", (select count(*) > 0 from submissions where for_problem = problems.id and submitter = ?1 and result like 'o%') as "user_solved!: bool", tests as "tests: Tests", visible as "visible: bool" from problems left join users on problems.created_by = users.id where problems.id = ?2"#,
+		r#"select name, description, problems.creation_time as "creation_time: Timestamp", users.id as "created_by_id?", users.display_name as "created_by_name?", (select count(*) from submissions where for_problem = problems.id) as "num_submissions!: i64", (select count(*) from submissions where for_problem = problems.id and result like 'o%') as "num_correct_submissions!: i64", (select count(*) > 0 from submissions where for_problem = problems.id and submitter = ?1 and result like 'o%') as "user_solved!: bool", tests as "tests: BlobKey", visible as "visible: bool" from problems left join users on problems.created_by = users.id where problems.id = ?2"#,
 		user_id,
 		problem_id,
 	)
 	.fetch_optional(&state.database)
 	.await
-	.map_err(error::sqlx(user.as_ref()))?
+	.map_err(error::from_db(user.as_ref()))?
 	else {
 		return Err(error::not_found(user.as_ref()).await);
 	};
@@ -268,16 +514,24 @@ async fn handler(
 		return Err(error::not_found(user.as_ref()).await);
 	}
 
-	let (sample_input, sample_output) = problem.tests.cases().next().unwrap();
+	let tests = state
+		.blobs
+		.get(problem.tests)
+		.await
+		.map_err(error::internal(user.as_ref()))?;
+	let tests = String::from_utf8(tests).map_err(error::internal(user.as_ref()))?;
+	let tests = Tests::try_from(tests).map_err(error::internal(user.as_ref()))?;
+
+	let (sample_input, sample_output) = tests.cases().next().unwrap();
 
 	let pass_rate = pass_rate(problem.num_submissions, problem.num_correct_submissions);
 
 	let body = html! {
-		@if permission_level >= ProblemPermissionLevel::Edit {
+		@if let (true, Some(edit_user)) = (permission_level >= ProblemPermissionLevel::Edit, &user) {
 			div.row {
 				a href={"/problem/"(problem_id)"/edit"} { "Edit" }
-				form method="post" action={"/problem/"(problem_id)"/delete"} { input type="submit" value="Delete"; }
-				@if user.as_ref().is_some_and(|user| user.permission_level >= PermissionLevel::Admin) {
+				form method="post" action={"/problem/"(problem_id)"/delete"} { (csrf_field(edit_user)) input type="submit" value="Delete"; }
+				@if edit_user.permission_level >= PermissionLevel::Admin {
 					a href={"/submissions?problem_id="(problem_id)} { "View submissions" }
 					a href={"/problem/"(problem_id)"/cases"} { "Download cases" }
 				}
@@ -341,6 +595,6 @@ pub fn router() -> axum::Router<Arc<State>> {
 		.route("/", get(handler).post(handler))
 		.route("/edit", get(edit_handler).post(edit_handler))
 		.route("/delete", post(delete_handler))
-		.route("/cases", get(download_cases));
+		.route("/cases", get(download_cases).post(upload_cases));
 	axum::Router::new().nest("/:id", router)
 }
@@ -16,7 +16,7 @@ use crate::model::PermissionLevel;
 use crate::template::{page, BannerKind};
 use crate::time::now;
 use crate::util::deserialize_non_empty;
-use crate::State;
+use crate::{ratelimit, State};
 
 #[derive(Deserialize)]
 struct Form {
@@ -34,7 +34,7 @@ async fn handle_post(state: &State, request: &Form) -> Result<(), ErrorResponse>
 	let user = query!("select id from users where username = ?", request.username)
 		.fetch_optional(&state.database)
 		.await
-		.map_err(ErrorResponse::sqlx)?;
+		.map_err(ErrorResponse::from_db)?;
 
 	if user.is_some() {
 		return Err(ErrorResponse::bad_request("The username is already taken."));
@@ -52,7 +52,7 @@ async fn handle_post(state: &State, request: &Form) -> Result<(), ErrorResponse>
 	)
 	.fetch_one(&state.database)
 	.await
-	.map_err(ErrorResponse::sqlx)?;
+	.map_err(ErrorResponse::from_db)?;
 
 	let password_hash = crate::password::hash(&request.password);
 	query!(
@@ -62,7 +62,7 @@ async fn handle_post(state: &State, request: &Form) -> Result<(), ErrorResponse>
 	)
 	.execute(&state.database)
 	.await
-	.map_err(ErrorResponse::sqlx)?;
+	.map_err(ErrorResponse::from_db)?;
 
 	Ok(())
 }
@@ -103,6 +103,8 @@ async fn handler(
 	(status, page).into_response()
 }
 
-pub fn router() -> axum::Router<Arc<State>> {
-	axum::Router::new().route("/register", get(handler).post(handler))
+pub fn router(state: Arc<State>) -> axum::Router<Arc<State>> {
+	axum::Router::new()
+		.route("/register", get(handler).post(handler))
+		.route_layer(ratelimit::layer(state, ratelimit::RouteClass::Auth))
 }
@@ -6,40 +6,61 @@ use axum::routing::{get, post};
 use maud::html;
 use sqlx::{query, query_scalar};
 
+use crate::blob_store::{BlobKey, BlobStore};
 use crate::error::ErrorResponse;
 use crate::extract::auth::User;
+use crate::extract::csrf::Csrf;
 use crate::extract::pagination::RawPagination;
+use crate::mod_log;
 use crate::model::{
-	Language, PermissionLevel, ProblemId, SimpleTestResponse, SubmissionId, UserId,
+	JudgeStatus, Language, PermissionLevel, ProblemId, SimpleTestResponse, SubmissionId, UserId,
 };
 use crate::sandbox::{Test, TestResponse};
-use crate::template::page;
+use crate::template::{csrf_field, page};
 use crate::time::{now, Timestamp};
 use crate::util::encode_query;
 use crate::{error, State};
 
-pub async fn do_judge(
-	state: &State,
-	submission_id: SubmissionId,
-) -> Result<Response, ErrorResponse> {
+/// Body of a POST form that carries no data of its own, only the `_csrf` field checked by
+/// [`Csrf`].
+#[derive(serde::Deserialize)]
+struct EmptyForm {}
+
+/// Actually runs the sandbox and records the result. Called by the judge queue workers, never directly from a request handler.
+pub async fn do_judge(state: &State, submission_id: SubmissionId) -> Result<(), ErrorResponse> {
+	query!(
+		"update submissions set judge_status = ? where id = ?",
+		JudgeStatus::Judging,
+		submission_id,
+	)
+	.execute(&state.database)
+	.await
+	.map_err(ErrorResponse::from_db)?;
+
 	let Some(submission) = query!(
-		r#"select code, language as "language: Language", problems.time_limit as "time_limit: u32", problems.tests, problems.custom_judger from submissions inner join problems on submissions.for_problem = problems.id where submissions.id = ?"#,
+		r#"select code as "code: BlobKey", language as "language: Language", problems.time_limit as "time_limit: u32", problems.tests as "tests: BlobKey", problems.custom_judger from submissions inner join problems on submissions.for_problem = problems.id where submissions.id = ?"#,
 		submission_id
 	)
 	.fetch_optional(&state.database)
 	.await
-	.map_err(ErrorResponse::sqlx)?
+	.map_err(ErrorResponse::from_db)?
 	else {
 		return Err(ErrorResponse::not_found().await);
 	};
 
+	let code = state.blobs.get(submission.code).await.map_err(ErrorResponse::internal)?;
+	let code = String::from_utf8(code).map_err(ErrorResponse::internal)?;
+
+	let tests = state.blobs.get(submission.tests).await.map_err(ErrorResponse::internal)?;
+	let tests = String::from_utf8(tests).map_err(ErrorResponse::internal)?;
+
 	let response = state
 		.sandbox
 		.test(&Test {
 			language: submission.language,
 			time_limit: submission.time_limit,
-			code: &submission.code,
-			tests: &submission.tests,
+			code: &code,
+			tests: &tests,
 			custom_judger: submission.custom_judger.as_deref(),
 		})
 		.await
@@ -48,20 +69,43 @@ pub async fn do_judge(
 	let now = now();
 
 	query!(
-		"update submissions set judged_time = ?, result = ? where id = ?",
+		"update submissions set judge_status = null, judged_time = ?, result = ? where id = ?",
 		now,
 		response,
 		submission_id,
 	)
 	.execute(&state.database)
 	.await
-	.map_err(ErrorResponse::sqlx)?;
+	.map_err(ErrorResponse::from_db)?;
 
-	Ok(Redirect::to(&format!("/submission/{submission_id}")).into_response())
+	Ok(())
+}
+
+/// Marks `submission_id` as queued and hands it to the background judge workers.
+pub async fn enqueue_for_judging(
+	state: &State,
+	submission_id: SubmissionId,
+) -> Result<(), ErrorResponse> {
+	query!(
+		"update submissions set judge_status = ?, judged_time = null, result = null where id = ?",
+		JudgeStatus::Queued,
+		submission_id,
+	)
+	.execute(&state.database)
+	.await
+	.map_err(ErrorResponse::from_db)?;
+
+	state
+		.judge_queue
+		.enqueue(submission_id)
+		.await
+		.map_err(ErrorResponse::internal)?;
+
+	Ok(())
 }
 
 #[derive(Debug, Clone, Copy)]
-enum SubmissionPermissionLevel {
+pub(crate) enum SubmissionPermissionLevel {
 	None,
 	View,
 	Edit,
@@ -83,7 +127,7 @@ impl SubmissionPermissionLevel {
 	}
 }
 
-fn permission_level(
+pub(crate) fn permission_level(
 	login_user: Option<&User>,
 	submitter: UserId,
 	problem_author: Option<UserId>,
@@ -109,8 +153,9 @@ async fn rejudge(
 	extract::State(state): extract::State<Arc<State>>,
 	user: Option<User>,
 	extract::Path(submission_id): extract::Path<SubmissionId>,
+	Csrf(extract::Form(EmptyForm {})): Csrf<extract::Form<EmptyForm>>,
 ) -> Result<Response, Response> {
-	let Some(submission) = query!("select submitter, problems.created_by as problem_author from submissions inner join problems on submissions.for_problem = problems.id where submissions.id = ?", submission_id).fetch_optional(&state.database).await.map_err(error::sqlx(user.as_ref()))? else {
+	let Some(submission) = query!("select submitter, problems.created_by as problem_author from submissions inner join problems on submissions.for_problem = problems.id where submissions.id = ?", submission_id).fetch_optional(&state.database).await.map_err(error::from_db(user.as_ref()))? else {
 		return Err(error::not_found(user.as_ref()).await);
 	};
 
@@ -124,17 +169,32 @@ async fn rejudge(
 		return Err(error::fake_not_found(user.as_ref()).await);
 	}
 
-	do_judge(&state, submission_id)
+	let actor = user.as_ref().expect("can_edit implies a logged-in user").id;
+	mod_log::record(
+		&state,
+		actor,
+		mod_log::Action::RejudgeSubmission,
+		mod_log::TargetType::Submission,
+		submission_id,
+		None,
+	)
+	.await
+	.map_err(error::from_db(user.as_ref()))?;
+
+	enqueue_for_judging(&state, submission_id)
 		.await
-		.map_err(|error| error.into_response(user.as_ref()))
+		.map_err(|error| error.into_response(user.as_ref()))?;
+
+	Ok(Redirect::to(&format!("/submission/{submission_id}")).into_response())
 }
 
 async fn delete(
 	extract::State(state): extract::State<Arc<State>>,
 	user: Option<User>,
 	extract::Path(submission_id): extract::Path<SubmissionId>,
+	Csrf(extract::Form(EmptyForm {})): Csrf<extract::Form<EmptyForm>>,
 ) -> Result<Response, Response> {
-	let Some(submission) = query!("select submitter, for_problem, problems.created_by as problem_author from submissions inner join problems on submissions.for_problem = problems.id where submissions.id = ?", submission_id).fetch_optional(&state.database).await.map_err(error::sqlx(user.as_ref()))? else {
+	let Some(submission) = query!("select submitter, for_problem, problems.created_by as problem_author from submissions inner join problems on submissions.for_problem = problems.id where submissions.id = ?", submission_id).fetch_optional(&state.database).await.map_err(error::from_db(user.as_ref()))? else {
 		return Err(error::not_found(user.as_ref()).await);
 	};
 
@@ -151,7 +211,20 @@ async fn delete(
 	query!("delete from submissions where id = ?", submission_id)
 		.execute(&state.database)
 		.await
-		.map_err(error::sqlx(user.as_ref()))?;
+		.map_err(error::from_db(user.as_ref()))?;
+
+	let actor = user.as_ref().expect("can_edit implies a logged-in user").id;
+	mod_log::record(
+		&state,
+		actor,
+		mod_log::Action::DeleteSubmission,
+		mod_log::TargetType::Submission,
+		submission_id,
+		None,
+	)
+	.await
+	.map_err(error::from_db(user.as_ref()))?;
+
 	Ok(Redirect::to(&format!("/problem/{}", submission.for_problem)).into_response())
 }
 
@@ -160,7 +233,7 @@ async fn handler(
 	user: Option<User>,
 	extract::Path(submission_id): extract::Path<SubmissionId>,
 ) -> Result<Response, Response> {
-	let Some(submission) = query!(r#"select code, for_problem as problem_id, problem.name as problem_name, problem.created_by as problem_author, submitter, submitter.display_name as submitter_name, language as "language: Language", submission_time as "submission_time: Timestamp", judged_time as "judged_time: Timestamp", result as "result: TestResponse" from submissions inner join problems as problem on submissions.for_problem = problem.id inner join users as submitter on submissions.submitter = submitter.id where submissions.id = ?"#, submission_id).fetch_optional(&state.database).await.map_err(error::sqlx(user.as_ref()))? else {
+	let Some(submission) = query!(r#"select code as "code: BlobKey", for_problem as problem_id, problem.name as problem_name, problem.created_by as problem_author, submitter, submitter.display_name as submitter_name, language as "language: Language", submission_time as "submission_time: Timestamp", judged_time as "judged_time: Timestamp", result as "result: TestResponse", judge_status as "judge_status: JudgeStatus" from submissions inner join problems as problem on submissions.for_problem = problem.id inner join users as submitter on submissions.submitter = submitter.id where submissions.id = ?"#, submission_id).fetch_optional(&state.database).await.map_err(error::from_db(user.as_ref()))? else {
 		return Err(error::not_found(user.as_ref()).await);
 	};
 
@@ -173,10 +246,17 @@ async fn handler(
 		return Err(error::fake_not_found(user.as_ref()).await);
 	}
 
+	let code = state
+		.blobs
+		.get(submission.code)
+		.await
+		.map_err(error::internal(user.as_ref()))?;
+	let code = String::from_utf8(code).map_err(error::internal(user.as_ref()))?;
+
 	let body = html! {
 		h1 { "Submission for " a href={"/problem/"(submission.problem_id)} { "Problem " (submission.problem_id) ": " (submission.problem_name) } }
-		@if permission_level.can_edit() {
-			form method="post" action={"/submission/"(submission_id)"/delete"} { input type="submit" value="Delete"; }
+		@if let (true, Some(user)) = (permission_level.can_edit(), &user) {
+			form method="post" action={"/submission/"(submission_id)"/delete"} { (csrf_field(user)) input type="submit" value="Delete"; }
 		}
 		p { b {
 			"By " (submission.submitter_name)
@@ -206,14 +286,21 @@ async fn handler(
 				p { "Program was invalid." }
 				pre { code { (reason) } }
 			},
-			None => p { "Program not yet judged." },
+			None => @match submission.judge_status {
+				Some(JudgeStatus::Queued) => p { "Queued for judging..." },
+				Some(JudgeStatus::Judging) => p { "Judging..." },
+				None => p { "Program not yet judged." },
+			},
 		}
-		form method="post" action={"/submission/"(submission_id)"/rejudge"} {
-			input type="submit" value="Rejudge";
+		@if let Some(user) = &user {
+			form method="post" action={"/submission/"(submission_id)"/rejudge"} {
+				(csrf_field(user))
+				input type="submit" value="Rejudge";
+			}
 		}
 		h2 { "Code" }
 		p { "Language: " (submission.language.name()) }
-		pre { code { (submission.code) } }
+		pre { code { (code) } }
 	};
 
 	let title = format!("Submission for Problem {}", submission.problem_id);
@@ -221,12 +308,12 @@ async fn handler(
 	Ok(page.custom_title().into_response())
 }
 
-const DEFAULT_PAGE_SIZE: u32 = 30;
+pub(crate) const DEFAULT_PAGE_SIZE: u32 = 30;
 
 macro_rules! search_query {
 	($($name:ident: $ty:ty,)*) => {
 		#[derive(serde::Deserialize)]
-		struct SubmissionsSearch {
+		pub(crate) struct SubmissionsSearch {
 			$(
 				#[serde(default)]
 				#[serde(deserialize_with = "crate::util::deserialize_non_empty")]
@@ -257,51 +344,212 @@ macro_rules! search_query {
 	};
 }
 
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum VerdictFilter {
+	Correct,
+	Wrong,
+	NotJudged,
+}
+
+impl VerdictFilter {
+	/// Numeric code bound into the submissions search query, which maps 0 to `result like 'o%'`
+	/// (correct), 1 to a judged but not correct result, and 2 to `result is null`.
+	pub(crate) fn repr(self) -> i64 {
+		match self {
+			Self::Correct => 0,
+			Self::Wrong => 1,
+			Self::NotJudged => 2,
+		}
+	}
+}
+
+impl std::str::FromStr for VerdictFilter {
+	type Err = String;
+
+	fn from_str(raw: &str) -> Result<Self, Self::Err> {
+		Ok(match raw {
+			"correct" => Self::Correct,
+			"wrong" => Self::Wrong,
+			"not_judged" => Self::NotJudged,
+			_ => return Err(format!("{raw:?} is not a recognized verdict filter")),
+		})
+	}
+}
+
+impl std::fmt::Display for VerdictFilter {
+	fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		formatter.write_str(match self {
+			Self::Correct => "correct",
+			Self::Wrong => "wrong",
+			Self::NotJudged => "not_judged",
+		})
+	}
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Sort {
+	TimeAsc,
+	TimeDesc,
+	VerdictAsc,
+	VerdictDesc,
+}
+
+impl Sort {
+	const DEFAULT: Self = Self::TimeDesc;
+}
+
+impl std::str::FromStr for Sort {
+	type Err = String;
+
+	fn from_str(raw: &str) -> Result<Self, Self::Err> {
+		Ok(match raw {
+			"time_asc" => Self::TimeAsc,
+			"time_desc" => Self::TimeDesc,
+			"verdict_asc" => Self::VerdictAsc,
+			"verdict_desc" => Self::VerdictDesc,
+			_ => return Err(format!("{raw:?} is not a recognized sort")),
+		})
+	}
+}
+
+impl std::fmt::Display for Sort {
+	fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		formatter.write_str(match self {
+			Self::TimeAsc => "time_asc",
+			Self::TimeDesc => "time_desc",
+			Self::VerdictAsc => "verdict_asc",
+			Self::VerdictDesc => "verdict_desc",
+		})
+	}
+}
+
 search_query! {
 	submitter: String,
 	submitter_id: UserId,
 	problem: String,
 	problem_id: ProblemId,
+	language: Language,
+	verdict: VerdictFilter,
+	submitted_after: Timestamp,
+	submitted_before: Timestamp,
+	sort: Sort,
 }
 
-async fn submissions(
-	extract::State(state): extract::State<Arc<State>>,
-	user: User,
-	pagination: RawPagination,
-	extract::Query(search): extract::Query<SubmissionsSearch>,
-) -> Result<Response, Response> {
-	let any_search = search.any_set();
+#[derive(Debug, serde::Serialize)]
+pub(crate) struct SubmissionListEntry {
+	pub submission_id: SubmissionId,
+	pub problem_id: ProblemId,
+	pub problem_name: String,
+	pub submitter_id: UserId,
+	pub submitter_name: String,
+	pub language: Language,
+	pub submission_time: Timestamp,
+	pub result: Option<SimpleTestResponse>,
+	pub judge_status: Option<JudgeStatus>,
+}
 
-	let pagination = pagination.with_default_page_size(DEFAULT_PAGE_SIZE);
-	let limit = pagination.limit();
-	let offset = pagination.offset();
+/// Applies `search`'s filters and `user`'s visibility rules, returning the total match count and one page of results. Shared between the HTML listing and the JSON API.
+pub(crate) async fn search(
+	state: &State,
+	user: &User,
+	search: &SubmissionsSearch,
+	limit: i64,
+	offset: i64,
+) -> Result<(i64, Vec<SubmissionListEntry>), sqlx::Error> {
+	let verdict_repr = search.verdict.map(VerdictFilter::repr);
 
 	let num_submissions = query_scalar!(
-		r#"select count(*) as "count: i64" from submissions inner join problems as problem on submissions.for_problem is problem.id inner join users as submitter on submissions.submitter is submitter.id where (?1 is null or instr(submitter.display_name, ?1) > 0) and (?2 is null or submissions.submitter is ?2) and (?3 is null or instr(problem.name, ?3) > 0) and (?4 is null or submissions.for_problem is ?4) and (?5 >= 20 or ?6 is submissions.submitter or (?5 >= 10 and ?6 is problem.created_by))"#,
+		r#"select count(*) as "count: i64" from submissions inner join problems as problem on submissions.for_problem is problem.id inner join users as submitter on submissions.submitter is submitter.id where (?1 is null or instr(submitter.display_name, ?1) > 0) and (?2 is null or submissions.submitter is ?2) and (?3 is null or instr(problem.name, ?3) > 0) and (?4 is null or submissions.for_problem is ?4) and (?5 is null or language is ?5) and (?6 is null or (?6 = 0 and submissions.result like 'o%') or (?6 = 1 and submissions.result is not null and submissions.result not like 'o%') or (?6 = 2 and submissions.result is null)) and (?7 is null or submissions.submission_time >= ?7) and (?8 is null or submissions.submission_time <= ?8) and (?9 >= 20 or ?10 is submissions.submitter or (?9 >= 10 and ?10 is problem.created_by))"#,
 		search.submitter,
 		search.submitter_id,
 		search.problem,
 		search.problem_id,
+		search.language,
+		verdict_repr,
+		search.submitted_after,
+		search.submitted_before,
 		user.permission_level,
 		user.id,
 	)
 	.fetch_one(&state.database)
-	.await
-	.map_err(error::sqlx(Some(&user)))?;
+	.await?;
+
+	macro_rules! submissions_query {
+		($order_by:literal) => {
+			query!(
+				concat!(
+					r#"select submissions.id as submission_id, problem.id as problem_id, problem.name as problem_name, submitter.id as submitter_id, submitter.display_name as submitter_name, language as "language: Language", submission_time as "submission_time: Timestamp", result as "result: SimpleTestResponse", judge_status as "judge_status: JudgeStatus" from submissions inner join problems as problem on submissions.for_problem = problem.id inner join users as submitter on submissions.submitter = submitter.id where (?3 is null or instr(submitter.display_name, ?3) > 0) and (?4 is null or submissions.submitter is ?4) and (?5 is null or instr(problem.name, ?5) > 0) and (?6 is null or submissions.for_problem is ?6) and (?7 is null or language is ?7) and (?8 is null or (?8 = 0 and submissions.result like 'o%') or (?8 = 1 and submissions.result is not null and submissions.result not like 'o%') or (?8 = 2 and submissions.result is null)) and (?9 is null or submissions.submission_time >= ?9) and (?10 is null or submissions.submission_time <= ?10) and (?11 >= 20 or ?12 is submissions.submitter or (?11 >= 10 and ?12 is problem.created_by)) order by "#,
+					$order_by,
+					" limit ?1 offset ?2"
+				),
+				limit,
+				offset,
+				search.submitter,
+				search.submitter_id,
+				search.problem,
+				search.problem_id,
+				search.language,
+				verdict_repr,
+				search.submitted_after,
+				search.submitted_before,
+				user.permission_level,
+				user.id,
+			)
+		};
+	}
 
-	let submissions = query!(
-		r#"select submissions.id as submission_id, problem.id as problem_id, problem.name as problem_name, submitter.id as submitter_id, submitter.display_name as submitter_name, language as "language: Language", submission_time as "submission_time: Timestamp", result as "result: SimpleTestResponse" from submissions inner join problems as problem on submissions.for_problem = problem.id inner join users as submitter on submissions.submitter = submitter.id where (?3 is null or instr(submitter.display_name, ?3) > 0) and (?4 is null or submissions.submitter is ?4) and (?5 is null or instr(problem.name, ?5) > 0) and (?6 is null or submissions.for_problem is ?6) and (?7 >= 20 or ?8 is submissions.submitter or (?7 >= 10 and ?8 is problem.created_by)) order by submissions.id desc limit ?1 offset ?2"#,
-		limit,
-		offset,
-		search.submitter,
-		search.submitter_id,
-		search.problem,
-		search.problem_id,
-		user.permission_level,
-		user.id,
-	).fetch_all(&state.database).await.map_err(error::sqlx(Some(&user)))?;
+	let submissions = match search.sort.unwrap_or(Sort::DEFAULT) {
+		Sort::TimeAsc => submissions_query!("submissions.submission_time asc, submissions.id asc")
+			.fetch_all(&state.database)
+			.await,
+		Sort::TimeDesc => submissions_query!("submissions.submission_time desc, submissions.id desc")
+			.fetch_all(&state.database)
+			.await,
+		Sort::VerdictAsc => submissions_query!("submissions.result asc, submissions.id desc")
+			.fetch_all(&state.database)
+			.await,
+		Sort::VerdictDesc => submissions_query!("submissions.result desc, submissions.id desc")
+			.fetch_all(&state.database)
+			.await,
+	}?;
+
+	let submissions = submissions
+		.into_iter()
+		.map(|row| SubmissionListEntry {
+			submission_id: row.submission_id,
+			problem_id: row.problem_id,
+			problem_name: row.problem_name,
+			submitter_id: row.submitter_id,
+			submitter_name: row.submitter_name,
+			language: row.language,
+			submission_time: row.submission_time,
+			result: row.result,
+			judge_status: row.judge_status,
+		})
+		.collect();
+
+	Ok((num_submissions, submissions))
+}
+
+async fn submissions(
+	extract::State(state): extract::State<Arc<State>>,
+	user: User,
+	pagination: RawPagination,
+	extract::Query(search_params): extract::Query<SubmissionsSearch>,
+) -> Result<Response, Response> {
+	let any_search = search_params.any_set();
+
+	let pagination = pagination.with_default_page_size(DEFAULT_PAGE_SIZE);
+	let limit = pagination.limit();
+	let offset = pagination.offset();
+
+	let (num_submissions, submissions) = search(&state, &user, &search_params, limit, offset)
+		.await
+		.map_err(error::from_db(Some(&user)))?;
+	let search = search_params;
 
 	let body = html! {
+		p { "Judge queue depth: " (state.judge_queue.depth()) }
 		details open[any_search] {
 			summary { "Search" }
 			form method="get" {
@@ -309,6 +557,33 @@ async fn submissions(
 				label { "Submitter ID" input type="number" name="submitter_id" value=[search.submitter_id]; }
 				label { "Problem name" input type="text" name="problem" value=[search.problem.as_deref()]; }
 				label { "Problem ID" input type="number" name="problem_id" value=[search.problem_id]; }
+				label {
+					"Language"
+					select name="language" {
+						option value="" { "(any)" }
+						@for language in Language::ALL { option value=(language.repr()) selected[search.language == Some(*language)] { (language.name()) } }
+					}
+				}
+				label {
+					"Verdict"
+					select name="verdict" {
+						option value="" { "(any)" }
+						option value="correct" selected[matches!(search.verdict, Some(VerdictFilter::Correct))] { "Correct" }
+						option value="wrong" selected[matches!(search.verdict, Some(VerdictFilter::Wrong))] { "Wrong" }
+						option value="not_judged" selected[matches!(search.verdict, Some(VerdictFilter::NotJudged))] { "Not yet judged" }
+					}
+				}
+				label { "Submitted after (unix timestamp)" input type="number" name="submitted_after" value=[search.submitted_after.map(i64::from)]; }
+				label { "Submitted before (unix timestamp)" input type="number" name="submitted_before" value=[search.submitted_before.map(i64::from)]; }
+				label {
+					"Sort by"
+					select name="sort" {
+						option value="time_desc" selected[matches!(search.sort, None | Some(Sort::TimeDesc))] { "Time (newest first)" }
+						option value="time_asc" selected[matches!(search.sort, Some(Sort::TimeAsc))] { "Time (oldest first)" }
+						option value="verdict_desc" selected[matches!(search.sort, Some(Sort::VerdictDesc))] { "Verdict (descending)" }
+						option value="verdict_asc" selected[matches!(search.sort, Some(Sort::VerdictAsc))] { "Verdict (ascending)" }
+					}
+				}
 				div.row {
 					input type="submit" value="Search";
 					// Intentionally resets pagination, because it's probably not useful.
@@ -331,7 +606,14 @@ async fn submissions(
 				td { a href={"/users/"(submission.submitter_id)} { (submission.submitter_name) } }
 				td { (submission.language.name()) }
 				td { (submission.submission_time) }
-				td { a href={"/submission/"(submission.submission_id)} { (submission.result.map_or("Not yet judged", SimpleTestResponse::as_str)) } }
+				td { a href={"/submission/"(submission.submission_id)} {
+					@match (&submission.result, submission.judge_status) {
+						(Some(result), _) => (result.as_str()),
+						(None, Some(JudgeStatus::Queued)) => "Queued",
+						(None, Some(JudgeStatus::Judging)) => "Judging",
+						(None, None) => "Not yet judged",
+					}
+				} }
 			} } }
 		}
 		@if submissions.is_empty() { p { "Nothing here..." } }
@@ -3,17 +3,27 @@ use std::sync::Arc;
 use axum::extract;
 use axum::response::{IntoResponse, Redirect, Response};
 use axum::routing::{get, post};
-use maud::html;
+use maud::{html, PreEscaped};
 use serde::Deserialize;
-use sqlx::query;
+use sqlx::{query, query_scalar};
 
+use crate::api_token;
 use crate::extract::auth::User;
-use crate::model::{PermissionLevel, UserId};
-use crate::template::{page, BannerKind};
-use crate::time::Timestamp;
+use crate::extract::csrf::Csrf;
+use crate::model::{Id, PermissionLevel, UserId};
+use crate::template::{csrf_field, page, BannerKind};
+use crate::time::{now, Timestamp};
+use crate::totp;
 use crate::util::s;
 use crate::{error, State};
 
+const TOTP_ISSUER: &str = "Autograder";
+
+/// Body of a POST form that carries no data of its own, only the `_csrf` field checked by
+/// [`Csrf`] — for handlers like [`delete`] that act purely on the path, with no other input.
+#[derive(Deserialize)]
+struct EmptyForm {}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 enum UserEditPermissionLevel {
 	None,
@@ -39,12 +49,45 @@ async fn main_page(
 	req_user_id: UserId,
 	action_message: Option<&str>,
 ) -> Result<Response, Response> {
-	let Some(req_user) = query!(r#"select username, display_name, email, creation_time as "creation_time!: Timestamp", permission_level as "permission_level: PermissionLevel", (select count(*) from submissions where submitter = users.id) as "total_submissions!: i64", (select count(distinct for_problem) from submissions where submitter = users.id and result like 'o%') as "solved_problems!: i64" from users where id = ?"#, req_user_id).fetch_optional(&state.database).await.map_err(error::internal(login_user))? else {
+	let Some(req_user) = query!(r#"select username, display_name, email, creation_time as "creation_time!: Timestamp", permission_level as "permission_level: PermissionLevel", blocked as "blocked!: bool", (select count(*) from submissions where submitter = users.id) as "total_submissions!: i64", (select count(distinct for_problem) from submissions where submitter = users.id and result like 'o%') as "solved_problems!: i64" from users where id = ?"#, req_user_id).fetch_optional(&state.database).await.map_err(error::internal(login_user))? else {
 		return Err(error::not_found(login_user).await);
 	};
 
 	let permission_level = permission_level(login_user, req_user_id);
 
+	let has_totp = query_scalar!(
+		r#"select count(*) as "count: i64" from totp_secrets where user = ?"#,
+		req_user_id,
+	)
+	.fetch_one(&state.database)
+	.await
+	.map_err(error::internal(login_user))?
+		> 0;
+
+	let sessions = if permission_level >= UserEditPermissionLevel::Edit {
+		query!(
+			r#"select rowid as "id!", creation_time as "creation_time!: Timestamp", expiration as "expiration!: Timestamp", user_agent, client_ip from sessions where user = ? order by creation_time desc"#,
+			req_user_id,
+		)
+		.fetch_all(&state.database)
+		.await
+		.map_err(error::internal(login_user))?
+	} else {
+		Vec::new()
+	};
+
+	let api_tokens = if permission_level >= UserEditPermissionLevel::Edit {
+		query!(
+			r#"select key_id as "key_id!", label, creation_time as "creation_time!: Timestamp" from api_tokens where user = ? order by creation_time desc"#,
+			req_user_id,
+		)
+		.fetch_all(&state.database)
+		.await
+		.map_err(error::internal(login_user))?
+	} else {
+		Vec::new()
+	};
+
 	let body = html! {
 		h1 { (req_user.display_name) " (" (req_user.username) ")" }
 		p { "Permission level: " (req_user.permission_level.name()) }
@@ -52,39 +95,127 @@ async fn main_page(
 		p { "Has made " (req_user.total_submissions) " submission" (s(req_user.total_submissions)) "." }
 		p { "Has solved " (req_user.solved_problems) " problem" (s(req_user.solved_problems)) "." }
 		@if permission_level >= UserEditPermissionLevel::Edit {
-			hr;
-			h2 { "Change display name" }
-			form method="post" action={"/users/"(req_user_id)"/rename"} {
-				label { "New display name" input type="text" name="display_name" required value=(req_user.display_name); }
-				input type="submit" value="Rename";
-			}
-			h2 { "Change email" }
-			form method="post" action={"/users/"(req_user_id)"/email"} {
-				label { "New email (empty for no email)" input type="text" name="email" value=[req_user.email]; }
-				input type="submit" value="Change";
-			}
-			h2 { "Change password" }
-			form method="post" action={"/users/"(req_user_id)"/password"} {
-				input type="password" autocomplete="new-password" name="password" required;
-				input type="submit" value="Change";
-			}
-			@if permission_level >= UserEditPermissionLevel::Admin {
-				h2 { "Change permission level" }
-				@if login_user.is_some_and(|login_user| login_user.id == req_user_id) {
-					p { "Be careful changing your own access, or you may lock yourself out." }
+			@if let Some(user) = login_user {
+				hr;
+				h2 { "Change display name" }
+				form method="post" action={"/users/"(req_user_id)"/rename"} {
+					(csrf_field(user))
+					label { "New display name" input type="text" name="display_name" required value=(req_user.display_name); }
+					input type="submit" value="Rename";
 				}
-				form method="post" action={"/users/"(req_user_id)"/permission"} {
-					label { "New permission level" select name="permission_level" required {
-						@for &level in PermissionLevel::ALL {
-							option value=(level.repr()) selected[level == req_user.permission_level] { (level.name()) }
-						}
-					} }
+				h2 { "Change email" }
+				form method="post" action={"/users/"(req_user_id)"/email"} {
+					(csrf_field(user))
+					label { "New email (empty for no email)" input type="text" name="email" value=[req_user.email]; }
 					input type="submit" value="Change";
 				}
-			}
-			h2 { "Delete" }
-			form method="post" action={"/users/"(req_user_id)"/delete"} {
-				input type="submit" value="Delete";
+				h2 { "Change password" }
+				form method="post" action={"/users/"(req_user_id)"/password"} {
+					(csrf_field(user))
+					input type="password" autocomplete="new-password" name="password" required;
+					input type="submit" value="Change";
+				}
+				h2 { "Two-factor authentication" }
+				@if has_totp {
+					p { "Two-factor authentication is enabled." }
+					form method="post" action={"/users/"(req_user_id)"/totp/disable"} {
+						(csrf_field(user))
+						input type="submit" value="Disable two-factor authentication";
+					}
+				} @else {
+					p { "Two-factor authentication is not enabled." }
+					form method="post" action={"/users/"(req_user_id)"/totp/enroll"} {
+						(csrf_field(user))
+						input type="submit" value="Enable two-factor authentication";
+					}
+				}
+				h2 { "Active sessions" }
+				@if sessions.is_empty() {
+					p { "No active sessions." }
+				} @else {
+					table {
+						thead { tr { th { "Created" } th { "Expires" } th { "User agent" } th { "IP" } th {} } }
+						tbody { @for session in &sessions {
+							tr {
+								td { (session.creation_time) }
+								td { (session.expiration) }
+								td { (session.user_agent.as_deref().unwrap_or("-")) }
+								td { (session.client_ip.as_deref().unwrap_or("-")) }
+								td {
+									form method="post" action={"/users/"(req_user_id)"/sessions/revoke"} {
+										(csrf_field(user))
+										input type="hidden" name="id" value=(session.id);
+										input type="submit" value="Revoke";
+									}
+								}
+							}
+						} }
+					}
+					form method="post" action={"/users/"(req_user_id)"/sessions/revoke-all"} {
+						(csrf_field(user))
+						input type="submit" value="Log out everywhere";
+					}
+				}
+				h2 { "API tokens" }
+				p { "API tokens authenticate programmatic access to the JSON API (under " code { "/api/v1" } ") in place of a session cookie." }
+				@if api_tokens.is_empty() {
+					p { "No API tokens." }
+				} @else {
+					table {
+						thead { tr { th { "Label" } th { "Created" } th {} } }
+						tbody { @for api_token in &api_tokens {
+							tr {
+								td { (api_token.label.as_deref().unwrap_or("-")) }
+								td { (api_token.creation_time) }
+								td {
+									form method="post" action={"/users/"(req_user_id)"/api-tokens/revoke"} {
+										(csrf_field(user))
+										input type="hidden" name="key_id" value=(api_token.key_id);
+										input type="submit" value="Revoke";
+									}
+								}
+							}
+						} }
+					}
+				}
+				form method="post" action={"/users/"(req_user_id)"/api-tokens/create"} {
+					(csrf_field(user))
+					label { "Label (optional)" input type="text" name="label"; }
+					input type="submit" value="Create API token";
+				}
+				@if permission_level >= UserEditPermissionLevel::Admin {
+					h2 { "Change permission level" }
+					@if user.id == req_user_id {
+						p { "Be careful changing your own access, or you may lock yourself out." }
+					}
+					form method="post" action={"/users/"(req_user_id)"/permission"} {
+						(csrf_field(user))
+						label { "New permission level" select name="permission_level" required {
+							@for &level in PermissionLevel::ALL {
+								option value=(level.repr()) selected[level == req_user.permission_level] { (level.name()) }
+							}
+						} }
+						input type="submit" value="Change";
+					}
+					h2 { "Login access" }
+					@if req_user.blocked {
+						p { "This account is blocked from logging in." }
+						form method="post" action={"/users/"(req_user_id)"/unblock"} {
+							(csrf_field(user))
+							input type="submit" value="Unblock";
+						}
+					} @else {
+						form method="post" action={"/users/"(req_user_id)"/block"} {
+							(csrf_field(user))
+							input type="submit" value="Block from logging in";
+						}
+					}
+				}
+				h2 { "Delete" }
+				form method="post" action={"/users/"(req_user_id)"/delete"} {
+					(csrf_field(user))
+					input type="submit" value="Delete";
+				}
 			}
 		}
 	};
@@ -101,6 +232,7 @@ async fn delete(
 	extract::State(state): extract::State<Arc<State>>,
 	login_user: Option<User>,
 	extract::Path(req_user_id): extract::Path<UserId>,
+	Csrf(extract::Form(EmptyForm {})): Csrf<extract::Form<EmptyForm>>,
 ) -> Result<Response, Response> {
 	if permission_level(login_user.as_ref(), req_user_id) < UserEditPermissionLevel::Edit {
 		return Err(error::fake_not_found(login_user.as_ref()).await);
@@ -125,7 +257,7 @@ async fn change_email(
 	extract::State(state): extract::State<Arc<State>>,
 	login_user: Option<User>,
 	extract::Path(req_user_id): extract::Path<UserId>,
-	extract::Form(post): extract::Form<ChangeEmailForm>,
+	Csrf(extract::Form(post)): Csrf<extract::Form<ChangeEmailForm>>,
 ) -> Result<Response, Response> {
 	if permission_level(login_user.as_ref(), req_user_id) < UserEditPermissionLevel::Admin {
 		return Err(error::fake_not_found(login_user.as_ref()).await);
@@ -133,6 +265,14 @@ async fn change_email(
 
 	let email = post.email.filter(|email| !email.is_empty());
 
+	let display_name = query_scalar!(
+		r#"select display_name as "display_name!" from users where id = ?"#,
+		req_user_id,
+	)
+	.fetch_one(&state.database)
+	.await
+	.map_err(error::internal(login_user.as_ref()))?;
+
 	query!(
 		"update users set email = ? where id = ?",
 		email,
@@ -142,11 +282,22 @@ async fn change_email(
 	.await
 	.map_err(error::internal(login_user.as_ref()))?;
 
+	if let Some(email) = &email {
+		crate::routes::password_reset::send_verification_email(
+			&state,
+			req_user_id,
+			&display_name,
+			email,
+		)
+		.await
+		.map_err(|error| error.into_response(login_user.as_ref()))?;
+	}
+
 	main_page(
 		&state,
 		login_user.as_ref(),
 		req_user_id,
-		Some("Email updated."),
+		Some("Email updated. Check your inbox for a verification link."),
 	)
 	.await
 }
@@ -160,7 +311,7 @@ async fn change_password(
 	extract::State(state): extract::State<Arc<State>>,
 	login_user: Option<User>,
 	extract::Path(req_user_id): extract::Path<UserId>,
-	extract::Form(post): extract::Form<ChangePasswordForm>,
+	Csrf(extract::Form(post)): Csrf<extract::Form<ChangePasswordForm>>,
 ) -> Result<Response, Response> {
 	if permission_level(login_user.as_ref(), req_user_id) < UserEditPermissionLevel::Edit {
 		return Err(error::fake_not_found(login_user.as_ref()).await);
@@ -194,7 +345,7 @@ async fn change_permission(
 	extract::State(state): extract::State<Arc<State>>,
 	login_user: Option<User>,
 	extract::Path(req_user_id): extract::Path<UserId>,
-	extract::Form(post): extract::Form<ChangePermissionForm>,
+	Csrf(extract::Form(post)): Csrf<extract::Form<ChangePermissionForm>>,
 ) -> Result<Response, Response> {
 	if permission_level(login_user.as_ref(), req_user_id) < UserEditPermissionLevel::Admin {
 		return Err(error::fake_not_found(login_user.as_ref()).await);
@@ -218,6 +369,51 @@ async fn change_permission(
 	.await
 }
 
+async fn set_blocked(
+	state: &State,
+	login_user: Option<&User>,
+	req_user_id: UserId,
+	blocked: bool,
+) -> Result<Response, Response> {
+	if permission_level(login_user, req_user_id) < UserEditPermissionLevel::Admin {
+		return Err(error::fake_not_found(login_user).await);
+	}
+
+	query!(
+		"update users set blocked = ? where id = ?",
+		blocked,
+		req_user_id,
+	)
+	.execute(&state.database)
+	.await
+	.map_err(error::internal(login_user))?;
+
+	let message = if blocked {
+		"User blocked from logging in."
+	} else {
+		"User unblocked."
+	};
+	main_page(state, login_user, req_user_id, Some(message)).await
+}
+
+async fn block(
+	extract::State(state): extract::State<Arc<State>>,
+	login_user: Option<User>,
+	extract::Path(req_user_id): extract::Path<UserId>,
+	Csrf(extract::Form(EmptyForm {})): Csrf<extract::Form<EmptyForm>>,
+) -> Result<Response, Response> {
+	set_blocked(&state, login_user.as_ref(), req_user_id, true).await
+}
+
+async fn unblock(
+	extract::State(state): extract::State<Arc<State>>,
+	login_user: Option<User>,
+	extract::Path(req_user_id): extract::Path<UserId>,
+	Csrf(extract::Form(EmptyForm {})): Csrf<extract::Form<EmptyForm>>,
+) -> Result<Response, Response> {
+	set_blocked(&state, login_user.as_ref(), req_user_id, false).await
+}
+
 #[derive(Deserialize)]
 struct RenameForm {
 	display_name: String,
@@ -227,7 +423,7 @@ async fn rename(
 	extract::State(state): extract::State<Arc<State>>,
 	login_user: Option<User>,
 	extract::Path(req_user_id): extract::Path<UserId>,
-	extract::Form(post): extract::Form<RenameForm>,
+	Csrf(extract::Form(post)): Csrf<extract::Form<RenameForm>>,
 ) -> Result<Response, Response> {
 	if permission_level(login_user.as_ref(), req_user_id) < UserEditPermissionLevel::Edit {
 		return Err(error::fake_not_found(login_user.as_ref()).await);
@@ -251,6 +447,238 @@ async fn rename(
 	.await
 }
 
+async fn totp_enroll(
+	extract::State(state): extract::State<Arc<State>>,
+	login_user: Option<User>,
+	extract::Path(req_user_id): extract::Path<UserId>,
+	Csrf(extract::Form(EmptyForm {})): Csrf<extract::Form<EmptyForm>>,
+) -> Result<Response, Response> {
+	if permission_level(login_user.as_ref(), req_user_id) < UserEditPermissionLevel::Edit {
+		return Err(error::fake_not_found(login_user.as_ref()).await);
+	}
+
+	let Some(username) = query_scalar!(r#"select username as "username!" from users where id = ?"#, req_user_id)
+		.fetch_optional(&state.database)
+		.await
+		.map_err(error::internal(login_user.as_ref()))?
+	else {
+		return Err(error::not_found(login_user.as_ref()).await);
+	};
+
+	let secret = totp::Secret::generate();
+	let recovery_codes: Vec<String> = std::iter::repeat_with(totp::generate_recovery_code)
+		.take(8)
+		.collect();
+
+	query!(
+		"insert into totp_secrets (user, secret, last_accepted_step) values (?, ?, null) on conflict (user) do update set secret = excluded.secret, last_accepted_step = null",
+		req_user_id,
+		secret,
+	)
+	.execute(&state.database)
+	.await
+	.map_err(error::internal(login_user.as_ref()))?;
+
+	query!("delete from recovery_codes where user = ?", req_user_id)
+		.execute(&state.database)
+		.await
+		.map_err(error::internal(login_user.as_ref()))?;
+	for recovery_code in &recovery_codes {
+		let hash = crate::password::hash(recovery_code);
+		query!(
+			"insert into recovery_codes (user, hash) values (?, ?)",
+			req_user_id,
+			hash,
+		)
+		.execute(&state.database)
+		.await
+		.map_err(error::internal(login_user.as_ref()))?;
+	}
+
+	let uri = secret.provisioning_uri(TOTP_ISSUER, &username);
+	let qr_svg = qrcode::QrCode::new(&uri)
+		.map_err(error::internal(login_user.as_ref()))?
+		.render::<qrcode::render::svg::Color>()
+		.build();
+
+	let body = html! {
+		h1 { "Enable Two-Factor Authentication" }
+		p { "Scan this QR code with your authenticator app, or enter the secret manually." }
+		(PreEscaped(qr_svg))
+		p { "Secret: " code { (secret.to_base32()) } }
+		h2 { "Recovery codes" }
+		p { "Save these somewhere safe. Each can be used once in place of a code from your authenticator app if you lose access to it. They will not be shown again." }
+		ul { @for recovery_code in &recovery_codes {
+			li { code { (recovery_code) } }
+		} }
+		p { a href={"/users/"(req_user_id)} { "Done" } }
+	};
+
+	Ok(page("Two-Factor Authentication", login_user.as_ref(), &body)
+		.custom_title()
+		.into_response())
+}
+
+async fn totp_disable(
+	extract::State(state): extract::State<Arc<State>>,
+	login_user: Option<User>,
+	extract::Path(req_user_id): extract::Path<UserId>,
+	Csrf(extract::Form(EmptyForm {})): Csrf<extract::Form<EmptyForm>>,
+) -> Result<Response, Response> {
+	if permission_level(login_user.as_ref(), req_user_id) < UserEditPermissionLevel::Edit {
+		return Err(error::fake_not_found(login_user.as_ref()).await);
+	}
+
+	query!("delete from totp_secrets where user = ?", req_user_id)
+		.execute(&state.database)
+		.await
+		.map_err(error::internal(login_user.as_ref()))?;
+	query!("delete from recovery_codes where user = ?", req_user_id)
+		.execute(&state.database)
+		.await
+		.map_err(error::internal(login_user.as_ref()))?;
+
+	main_page(
+		&state,
+		login_user.as_ref(),
+		req_user_id,
+		Some("Two-factor authentication disabled."),
+	)
+	.await
+}
+
+#[derive(Deserialize)]
+struct RevokeSessionForm {
+	id: Id,
+}
+
+async fn revoke_session(
+	extract::State(state): extract::State<Arc<State>>,
+	login_user: Option<User>,
+	extract::Path(req_user_id): extract::Path<UserId>,
+	Csrf(extract::Form(post)): Csrf<extract::Form<RevokeSessionForm>>,
+) -> Result<Response, Response> {
+	if permission_level(login_user.as_ref(), req_user_id) < UserEditPermissionLevel::Edit {
+		return Err(error::fake_not_found(login_user.as_ref()).await);
+	}
+
+	query!(
+		"delete from sessions where rowid = ? and user = ?",
+		post.id,
+		req_user_id,
+	)
+	.execute(&state.database)
+	.await
+	.map_err(error::internal(login_user.as_ref()))?;
+
+	main_page(
+		&state,
+		login_user.as_ref(),
+		req_user_id,
+		Some("Session revoked."),
+	)
+	.await
+}
+
+async fn revoke_all_sessions(
+	extract::State(state): extract::State<Arc<State>>,
+	login_user: Option<User>,
+	extract::Path(req_user_id): extract::Path<UserId>,
+	Csrf(extract::Form(EmptyForm {})): Csrf<extract::Form<EmptyForm>>,
+) -> Result<Response, Response> {
+	if permission_level(login_user.as_ref(), req_user_id) < UserEditPermissionLevel::Edit {
+		return Err(error::fake_not_found(login_user.as_ref()).await);
+	}
+
+	query!("delete from sessions where user = ?", req_user_id)
+		.execute(&state.database)
+		.await
+		.map_err(error::internal(login_user.as_ref()))?;
+
+	main_page(
+		&state,
+		login_user.as_ref(),
+		req_user_id,
+		Some("Logged out everywhere."),
+	)
+	.await
+}
+
+#[derive(Deserialize)]
+struct CreateApiTokenForm {
+	label: Option<String>,
+}
+
+async fn create_api_token(
+	extract::State(state): extract::State<Arc<State>>,
+	login_user: Option<User>,
+	extract::Path(req_user_id): extract::Path<UserId>,
+	Csrf(extract::Form(post)): Csrf<extract::Form<CreateApiTokenForm>>,
+) -> Result<Response, Response> {
+	if permission_level(login_user.as_ref(), req_user_id) < UserEditPermissionLevel::Edit {
+		return Err(error::fake_not_found(login_user.as_ref()).await);
+	}
+
+	let label = post.label.filter(|label| !label.is_empty());
+
+	let creation_time = now();
+	let res = query!(
+		"insert into api_tokens (user, label, creation_time) values (?, ?, ?)",
+		req_user_id,
+		label,
+		creation_time,
+	)
+	.execute(&state.database)
+	.await
+	.map_err(error::internal(login_user.as_ref()))?;
+
+	let token = api_token::encode(&state.api_token_secret, req_user_id, res.last_insert_rowid());
+
+	let body = html! {
+		h1 { "API Token Created" }
+		p { "Save this token somewhere safe. It will not be shown again." }
+		p { code { (token) } }
+		p { a href={"/users/"(req_user_id)} { "Done" } }
+	};
+
+	Ok(page("API Token Created", login_user.as_ref(), &body)
+		.custom_title()
+		.into_response())
+}
+
+#[derive(Deserialize)]
+struct RevokeApiTokenForm {
+	key_id: i64,
+}
+
+async fn revoke_api_token(
+	extract::State(state): extract::State<Arc<State>>,
+	login_user: Option<User>,
+	extract::Path(req_user_id): extract::Path<UserId>,
+	Csrf(extract::Form(post)): Csrf<extract::Form<RevokeApiTokenForm>>,
+) -> Result<Response, Response> {
+	if permission_level(login_user.as_ref(), req_user_id) < UserEditPermissionLevel::Edit {
+		return Err(error::fake_not_found(login_user.as_ref()).await);
+	}
+
+	query!(
+		"delete from api_tokens where key_id = ? and user = ?",
+		post.key_id,
+		req_user_id,
+	)
+	.execute(&state.database)
+	.await
+	.map_err(error::internal(login_user.as_ref()))?;
+
+	main_page(
+		&state,
+		login_user.as_ref(),
+		req_user_id,
+		Some("API token revoked."),
+	)
+	.await
+}
+
 async fn handler(
 	extract::State(state): extract::State<Arc<State>>,
 	login_user: Option<User>,
@@ -266,6 +694,14 @@ pub fn router() -> axum::Router<Arc<State>> {
 		.route("/email", post(change_email).get(handler))
 		.route("/password", post(change_password).get(handler))
 		.route("/permission", post(change_permission).get(handler))
-		.route("/rename", post(rename).get(handler));
+		.route("/rename", post(rename).get(handler))
+		.route("/totp/enroll", post(totp_enroll).get(handler))
+		.route("/totp/disable", post(totp_disable).get(handler))
+		.route("/sessions/revoke", post(revoke_session).get(handler))
+		.route("/sessions/revoke-all", post(revoke_all_sessions).get(handler))
+		.route("/api-tokens/create", post(create_api_token).get(handler))
+		.route("/api-tokens/revoke", post(revoke_api_token).get(handler))
+		.route("/block", post(block).get(handler))
+		.route("/unblock", post(unblock).get(handler));
 	axum::Router::new().nest("/users/:id", router)
 }
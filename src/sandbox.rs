@@ -15,26 +15,42 @@ enum Command<'a> {
 	Versions,
 }
 
-#[derive(Debug, Clone, Copy, Deserialize)]
-pub enum CaseResult {
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaseResultKind {
 	Correct,
 	Wrong,
 	RuntimeError,
-	Timeout,
+	TimeLimitExceeded,
+	MemoryLimitExceeded,
 }
 
-impl CaseResult {
+impl CaseResultKind {
 	pub fn as_str(self) -> &'static str {
 		match self {
 			Self::Correct => "Correct ✅",
 			Self::Wrong => "Wrong ❌",
 			Self::RuntimeError => "Runtime error 💥",
-			Self::Timeout => "Timeout ⌛",
+			Self::TimeLimitExceeded => "Timeout ⌛",
+			Self::MemoryLimitExceeded => "Out of memory 💾",
 		}
 	}
 }
 
-#[derive(Debug, Deserialize)]
+/// A single test case's result, as reported by the runner: which of [`CaseResultKind`] it fell
+/// into, and the resources it used (for display, and so slow-but-correct solutions can be told
+/// apart from comfortably-fast ones).
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+pub struct CaseResult {
+	pub kind: CaseResultKind,
+	/// Peak memory usage, in bytes.
+	pub memory_usage: u64,
+	/// Wall-clock run time, in milliseconds.
+	pub time: u64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum TestResponse {
 	Ok(Vec<CaseResult>),
 	InvalidProgram(String),
@@ -189,4 +205,8 @@ impl Sandbox {
 	pub fn versions(&self) -> &[Box<str>] {
 		&self.versions
 	}
+
+	pub fn image_id(&self) -> &str {
+		&self.image_id
+	}
 }
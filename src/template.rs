@@ -44,6 +44,19 @@ const FOOTER: PreEscaped<&str> = PreEscaped(
 	r#"<p>Autograder is free and libre open-source software (FLOSS) licensed under the GNU Affero General Public License version 3.0 (AGPLv3). The full text of the license is available at <a href="https://www.gnu.org/licenses/agpl-3.0.en.html" target="_blank">https://www.gnu.org/licenses/agpl-3.0.en.html</a>.</p><p>Under this license you have the right as a user to access the source code. It is available at <a href="https://github.com/mattfbacon/autograder" target="_blank">https://github.com/mattfbacon/autograder</a>.</p>"#,
 );
 
+/// A hidden `_csrf` field matching the current session's anti-CSRF token, for use inside `form!`
+/// blocks that `post` to a handler guarded by [`crate::extract::csrf::Csrf`].
+pub fn csrf_field(user: &User) -> Markup {
+	html! { input type="hidden" name="_csrf" value=(user.csrf_token); }
+}
+
+/// A `_csrf=...` query-string fragment matching the current session's anti-CSRF token, for
+/// `enctype="multipart/form-data"` forms: append to the `action` URL, since [`csrf_field`]'s
+/// hidden input isn't an option when [`crate::extract::csrf::Csrf`] doesn't buffer the body.
+pub fn csrf_query(user: &User) -> String {
+	format!("_csrf={}", user.csrf_token)
+}
+
 fn navbar(user: Option<&User>) -> Markup {
 	html! { nav {
 		a href="/" {
@@ -88,6 +101,9 @@ impl<'a> Page<'a> {
 				head {
 					meta charset="UTF-8";
 					meta name="viewport" content="width=device-width,initial-scale=1";
+					@if let Some(user) = self.user {
+						meta name="csrf-token" content=(user.csrf_token);
+					}
 					title { (self.title) " - Autograder" }
 					link rel="icon" href="/res/favicon.svg" sizes="any" type="image/svg+xml";
 					link rel="icon" href="/res/favicon.png" sizes="48x48" type="image/png";
@@ -54,6 +54,15 @@ impl From<Timestamp> for i64 {
 	}
 }
 
+/// Parses a raw unix timestamp (seconds since the epoch), as used e.g. in search query parameters.
+impl std::str::FromStr for Timestamp {
+	type Err = std::num::ParseIntError;
+
+	fn from_str(raw: &str) -> Result<Self, Self::Err> {
+		raw.parse().map(Self::from)
+	}
+}
+
 sqlx_type_via!(Timestamp as i64);
 
 impl std::fmt::Display for Timestamp {
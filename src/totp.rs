@@ -0,0 +1,104 @@
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+
+use crate::time::Timestamp;
+
+const SECRET_LEN: usize = 20;
+const STEP_SECONDS: i64 = 30;
+const WINDOW_STEPS: i64 = 1;
+
+type SecretData = [u8; SECRET_LEN];
+
+/// An RFC 6238 shared secret. Stored raw (not hex) in the database, same as [`crate::extract::auth::Token`].
+#[derive(Clone)]
+pub struct Secret(SecretData);
+
+impl Secret {
+	#[must_use]
+	pub fn generate() -> Self {
+		Self(rand::random())
+	}
+
+	#[must_use]
+	pub fn to_base32(&self) -> String {
+		base32::encode(base32::Alphabet::RFC4648 { padding: false }, &self.0)
+	}
+
+	#[must_use]
+	pub fn provisioning_uri(&self, issuer: &str, account_name: &str) -> String {
+		format!(
+			"otpauth://totp/{issuer}:{account_name}?secret={secret}&issuer={issuer}&algorithm=SHA1&digits=6&period=30",
+			issuer = crate::util::encode_query(issuer.as_bytes()),
+			account_name = crate::util::encode_query(account_name.as_bytes()),
+			secret = self.to_base32(),
+		)
+	}
+
+	fn code_at_step(&self, step: i64) -> u32 {
+		let mut mac =
+			<Hmac<Sha1>>::new_from_slice(&self.0).expect("HMAC-SHA1 accepts keys of any length");
+		mac.update(&step.to_be_bytes());
+		let digest = mac.finalize().into_bytes();
+		let offset = (digest[digest.len() - 1] & 0xf) as usize;
+		let truncated =
+			u32::from_be_bytes(digest[offset..offset + 4].try_into().unwrap()) & 0x7fff_ffff;
+		truncated % 1_000_000
+	}
+
+	/// Checks `code` against the step for `at`, plus or minus [`WINDOW_STEPS`] to tolerate clock skew.
+	/// `last_accepted_step`, if given, excludes that step (and anything before it) to reject replays.
+	/// Returns the matched step on success, so the caller can persist it as the new `last_accepted_step`.
+	#[must_use]
+	pub fn verify(&self, code: &str, at: Timestamp, last_accepted_step: Option<i64>) -> Option<i64> {
+		if code.len() != 6 || !code.bytes().all(|byte| byte.is_ascii_digit()) {
+			return None;
+		}
+
+		let current_step = step_for(at);
+		(-WINDOW_STEPS..=WINDOW_STEPS)
+			.map(|delta| current_step + delta)
+			.filter(|&step| last_accepted_step.map_or(true, |last| step > last))
+			.find(|&step| format!("{:06}", self.code_at_step(step)) == code)
+	}
+}
+
+fn step_for(at: Timestamp) -> i64 {
+	i64::from(at) / STEP_SECONDS
+}
+
+impl sqlx::Type<sqlx::Sqlite> for Secret {
+	fn type_info() -> <sqlx::Sqlite as sqlx::Database>::TypeInfo {
+		<&[u8] as sqlx::Type<sqlx::Sqlite>>::type_info()
+	}
+
+	fn compatible(ty: &<sqlx::Sqlite as sqlx::Database>::TypeInfo) -> bool {
+		<&[u8] as sqlx::Type<sqlx::Sqlite>>::compatible(ty)
+	}
+}
+
+impl<'q> sqlx::Encode<'q, sqlx::Sqlite> for Secret {
+	#[allow(clippy::needless_borrow)]
+	fn encode_by_ref(
+		&self,
+		buf: &mut <sqlx::Sqlite as sqlx::database::HasArguments<'q>>::ArgumentBuffer,
+	) -> sqlx::encode::IsNull {
+		<Vec<u8> as sqlx::Encode<'q, sqlx::Sqlite>>::encode(self.0.into(), buf)
+	}
+}
+
+impl<'r> sqlx::Decode<'r, sqlx::Sqlite> for Secret {
+	fn decode(
+		value: <sqlx::Sqlite as sqlx::database::HasValueRef<'r>>::ValueRef,
+	) -> Result<Self, sqlx::error::BoxDynError> {
+		let bytes_slice = <&[u8] as sqlx::Decode<'r, sqlx::Sqlite>>::decode(value)?;
+		let bytes = bytes_slice.try_into()?;
+		Ok(Self(bytes))
+	}
+}
+
+/// Generates a random single-use recovery code. Only [`crate::password::hash`] of this is ever stored;
+/// the caller must show the plaintext to the user immediately, as it cannot be recovered afterward.
+#[must_use]
+pub fn generate_recovery_code() -> String {
+	hex::encode(rand::random::<[u8; 8]>())
+}
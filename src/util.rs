@@ -15,6 +15,16 @@ pub fn encode_query(raw: &[u8]) -> percent_encoding::PercentEncode<'_> {
 	percent_encoding::percent_encode(raw, &SET)
 }
 
+/// Trims `raw` and normalizes its line endings, as a textarea's contents are normalized whether
+/// they arrive through a `Form` (see [`deserialize_textarea`]) or through a multipart field.
+pub(crate) fn normalize_textarea(raw: &str) -> String {
+	let mut ret = raw.trim().replace("\r\n", "\n");
+	if !ret.is_empty() && !ret.ends_with('\n') {
+		ret.push('\n');
+	}
+	ret
+}
+
 pub fn deserialize_textarea<'de, D: serde::Deserializer<'de>>(de: D) -> Result<String, D::Error> {
 	struct Visitor;
 
@@ -29,11 +39,7 @@ pub fn deserialize_textarea<'de, D: serde::Deserializer<'de>>(de: D) -> Result<S
 		where
 			E: serde::de::Error,
 		{
-			let mut ret = v.trim().replace("\r\n", "\n");
-			if !ret.is_empty() && !ret.ends_with('\n') {
-				ret.push('\n');
-			}
-			Ok(ret)
+			Ok(normalize_textarea(v))
 		}
 
 		// TODO maybe implement `visit_string` to process in-place.
@@ -141,6 +147,14 @@ macro_rules! db_enum {
 				Self::from_repr(raw).ok_or_else(|| format!("{}i64 is not recognized as a {}", raw, stringify!($name)))
 			}
 		}
+
+		impl std::str::FromStr for $name {
+			type Err = String;
+
+			fn from_str(raw: &str) -> Result<Self, Self::Err> {
+				raw.parse::<i64>().map_err(|error| error.to_string())?.try_into()
+			}
+		}
 	};
 }
 pub(crate) use db_enum;
@@ -171,6 +185,33 @@ pub fn s(v: i64) -> &'static str {
 	}
 }
 
+/// Unpadded, URL-safe base64, as used by PKCE's `code_challenge` (RFC 7636).
+pub fn base64_url_no_pad(bytes: &[u8]) -> String {
+	const ALPHABET: &[u8; 64] =
+		b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+	let mut out = String::with_capacity((bytes.len() * 4 + 2) / 3);
+	for chunk in bytes.chunks(3) {
+		let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+		let n = u32::from_be_bytes([0, b[0], b[1], b[2]]);
+		out.push(ALPHABET[(n >> 18 & 0x3f) as usize] as char);
+		out.push(ALPHABET[(n >> 12 & 0x3f) as usize] as char);
+		if chunk.len() > 1 {
+			out.push(ALPHABET[(n >> 6 & 0x3f) as usize] as char);
+		}
+		if chunk.len() > 2 {
+			out.push(ALPHABET[(n & 0x3f) as usize] as char);
+		}
+	}
+	out
+}
+
+/// Compares two byte strings in time proportional only to their length, to avoid leaking
+/// information about where they first differ (relevant when comparing signatures/MACs).
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+	a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
 pub trait DivCeilPolyfill {
 	fn div_ceil_p(self, rhs: Self) -> Self;
 }